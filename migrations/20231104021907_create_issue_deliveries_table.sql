@@ -0,0 +1,26 @@
+CREATE TABLE issue_deliveries (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    status TEXT NOT NULL,
+    provider_message_id TEXT NULL,
+    last_error TEXT NULL,
+    queued_at timestamptz NOT NULL,
+    updated_at timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
+
+INSERT INTO issue_deliveries (newsletter_issue_id, subscriber_email, status, queued_at, updated_at)
+SELECT newsletter_issue_id, subscriber_email, 'queued', now(), now()
+FROM issue_delivery_queue;
+
+INSERT INTO issue_deliveries (
+    newsletter_issue_id,
+    subscriber_email,
+    status,
+    last_error,
+    queued_at,
+    updated_at
+)
+SELECT newsletter_issue_id, subscriber_email, 'failed', last_error, failed_at, failed_at
+FROM issue_delivery_dead_letters;
@@ -0,0 +1,2 @@
+-- Where draft issues are sent when an admin asks for a test email
+ALTER TABLE users ADD COLUMN email TEXT NULL;
@@ -0,0 +1,2 @@
+-- The source of issues authored in Markdown, from which both bodies are generated
+ALTER TABLE newsletter_issues ADD COLUMN markdown_content TEXT NULL;
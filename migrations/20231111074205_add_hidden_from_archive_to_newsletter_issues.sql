@@ -0,0 +1,2 @@
+-- Issues are listed in the public web archive unless opted out
+ALTER TABLE newsletter_issues ADD COLUMN hidden_from_archive BOOLEAN NOT NULL DEFAULT false;
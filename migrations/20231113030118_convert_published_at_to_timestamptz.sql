@@ -0,0 +1,4 @@
+-- Publish times were stored as text, which neither sorts nor compares reliably
+ALTER TABLE newsletter_issues
+    ALTER COLUMN published_at TYPE timestamptz USING published_at::timestamptz;
+CREATE INDEX newsletter_issues_published_at_idx ON newsletter_issues (published_at);
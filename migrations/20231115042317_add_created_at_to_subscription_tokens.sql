@@ -0,0 +1,2 @@
+-- Tokens issued before this migration start their validity period now
+ALTER TABLE subscription_tokens ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
@@ -0,0 +1,4 @@
+-- When a link was followed; a used token can no longer confirm anyone
+ALTER TABLE subscription_tokens ADD COLUMN used_at timestamptz;
+-- Unknown for subscribers who confirmed before this migration
+ALTER TABLE subscriptions ADD COLUMN confirmed_at timestamptz;
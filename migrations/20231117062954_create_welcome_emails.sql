@@ -0,0 +1,34 @@
+-- A single, admin-editable template; disabled until an admin has reviewed it
+CREATE TABLE welcome_email_template (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    enabled BOOLEAN NOT NULL,
+    subject TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    include_latest_issue BOOLEAN NOT NULL,
+    updated_at timestamptz NOT NULL
+);
+INSERT INTO welcome_email_template (
+    enabled,
+    subject,
+    html_content,
+    text_content,
+    include_latest_issue,
+    updated_at
+)
+VALUES (
+    FALSE,
+    'Welcome to our newsletter!',
+    '<p>Hi {{ subscriber.name }}, thanks for confirming your subscription!</p>' ||
+    '<p>You can <a href="{{ unsubscribe_url }}">unsubscribe</a> at any time.</p>',
+    E'Hi {{ subscriber.name }}, thanks for confirming your subscription!\n\n' ||
+    'You can unsubscribe at any time: {{ unsubscribe_url }}',
+    TRUE,
+    now()
+);
+
+CREATE TABLE welcome_email_queue (
+    subscriber_id uuid PRIMARY KEY REFERENCES subscriptions (id) ON DELETE CASCADE,
+    n_retries INT NOT NULL DEFAULT 0,
+    execute_after timestamptz NOT NULL DEFAULT now()
+);
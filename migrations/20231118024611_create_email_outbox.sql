@@ -0,0 +1,46 @@
+-- Transactional emails, written alongside the change that triggers them
+-- and delivered by the background worker
+CREATE TABLE email_outbox (
+    id uuid PRIMARY KEY,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    header_names TEXT[] NOT NULL,
+    header_values TEXT[] NOT NULL,
+    n_retries INT NOT NULL DEFAULT 0,
+    execute_after timestamptz NOT NULL DEFAULT now(),
+    created_at timestamptz NOT NULL DEFAULT now(),
+    -- Set on welcome emails: the worker renders them for this subscriber right before each
+    -- attempt, and drops them once the subscriber is no longer confirmed
+    welcome_subscriber_id uuid REFERENCES subscriptions (id) ON DELETE CASCADE
+);
+
+-- Welcome emails now go through the outbox. Those still waiting in the old queue are carried
+-- over.
+INSERT INTO email_outbox (
+    id,
+    recipient,
+    subject,
+    html_content,
+    text_content,
+    header_names,
+    header_values,
+    n_retries,
+    execute_after,
+    welcome_subscriber_id
+)
+SELECT
+    gen_random_uuid(),
+    s.email,
+    '',
+    '',
+    '',
+    '{}',
+    '{}',
+    q.n_retries,
+    q.execute_after,
+    q.subscriber_id
+FROM welcome_email_queue q
+JOIN subscriptions s ON s.id = q.subscriber_id;
+DROP TABLE welcome_email_queue;
@@ -0,0 +1,15 @@
+-- Transactional emails that exhausted their retry budget, until an admin re-enqueues them
+CREATE TABLE email_outbox_dead_letters (
+    id uuid PRIMARY KEY,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    header_names TEXT[] NOT NULL,
+    header_values TEXT[] NOT NULL,
+    n_retries INT NOT NULL,
+    last_error TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    failed_at timestamptz NOT NULL,
+    welcome_subscriber_id uuid REFERENCES subscriptions (id) ON DELETE CASCADE
+);
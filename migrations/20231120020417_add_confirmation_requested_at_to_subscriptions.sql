@@ -0,0 +1,2 @@
+-- When the latest confirmation email was enqueued, to throttle repeated sign-ups
+ALTER TABLE subscriptions ADD COLUMN confirmation_requested_at timestamptz NULL;
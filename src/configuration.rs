@@ -6,7 +6,10 @@ use sqlx::{
     ConnectOptions,
 };
 
-use crate::{domain::SubscriberEmail, email_client::EmailClient};
+use crate::{
+    domain::SubscriberEmail,
+    email_client::{EmailClient, FileTransport, PostmarkTransport, SmtpTransport},
+};
 
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
@@ -14,6 +17,8 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub email_client: EmailClientSettings,
     pub redis_uri: Secret<String>,
+    pub worker: WorkerSettings,
+    pub subscriptions: SubscriptionSettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -23,6 +28,15 @@ pub struct ApplicationSettings {
     pub port: u16,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    /// How long in-flight requests and deliveries get to complete once shutdown is requested.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub drain_timeout_seconds: u64,
+}
+
+impl ApplicationSettings {
+    pub fn drain_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.drain_timeout_seconds)
+    }
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -115,10 +129,39 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
 
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
+    #[serde(default)]
+    pub transport: EmailTransportKind,
     pub base_url: String,
     pub sender_email: String,
     pub authorization_token: Secret<String>,
     pub timeout_milliseconds: u64,
+    pub smtp: Option<SmtpSettings>,
+    pub file: Option<FileTransportSettings>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransportKind {
+    /// Postmark's HTTP API, configured by `base_url` and `authorization_token`.
+    #[default]
+    Postmark,
+    Smtp,
+    File,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    pub require_tls: bool,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct FileTransportSettings {
+    pub directory: String,
 }
 
 impl EmailClientSettings {
@@ -134,11 +177,117 @@ impl EmailClientSettings {
         let sender_email = self.sender().expect("Invalid sender email address.");
         let timeout = self.timeout();
 
-        EmailClient::new(
-            self.base_url,
-            sender_email,
-            self.authorization_token,
-            timeout,
-        )
+        match self.transport {
+            EmailTransportKind::Postmark => EmailClient::new(
+                sender_email,
+                PostmarkTransport::new(self.base_url, self.authorization_token, timeout),
+            ),
+            EmailTransportKind::Smtp => {
+                let smtp = self.smtp.expect("Missing `email_client.smtp` settings.");
+                let credentials = smtp.username.zip(smtp.password);
+                let transport = SmtpTransport::new(
+                    &smtp.host,
+                    smtp.port,
+                    credentials,
+                    smtp.require_tls,
+                    timeout,
+                )
+                .expect("Invalid SMTP settings.");
+                EmailClient::new(sender_email, transport)
+            }
+            EmailTransportKind::File => {
+                let file = self.file.expect("Missing `email_client.file` settings.");
+                let transport = FileTransport::new(file.directory)
+                    .expect("Failed to create the email output directory.");
+                EmailClient::new(sender_email, transport)
+            }
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct WorkerSettings {
+    /// How many times a failed delivery is retried before it is moved to the dead-letter table.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_retries: i32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub retry_base_delay_milliseconds: u64,
+    /// How many queued deliveries are dequeued and sent together.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub batch_size: i64,
+    /// How often an idle worker checks the queue when no notification wakes it up.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub poll_interval_milliseconds: u64,
+    /// How long a worker backs off after an unexpected error, e.g. the database being unreachable.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub error_backoff_milliseconds: u64,
+    /// How many delivery loops run concurrently within a single process. Must be at least 1.
+    ///
+    /// Each loop holds two database connections, one listening for notifications and one for
+    /// the batch it is executing: Postgres must accept `2 * concurrency + 1` connections per
+    /// worker process, see [`WorkerSettings::connection_pool_size`].
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub concurrency: usize,
+    /// The email provider's sending limit, shared by all delivery loops of a process.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub messages_per_second: u32,
+}
+
+impl WorkerSettings {
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.poll_interval_milliseconds)
+    }
+
+    pub fn error_backoff(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.error_backoff_milliseconds)
+    }
+
+    /// The connections a worker process needs: two per delivery loop, plus one for the cleanup
+    /// of abandoned subscriptions.
+    pub fn connection_pool_size(&self) -> u32 {
+        u32::try_from(self.concurrency)
+            .unwrap_or(u32::MAX)
+            .saturating_mul(2)
+            .saturating_add(1)
+    }
+
+    /// Exponential backoff: the base delay doubles with every retry already attempted.
+    pub fn retry_delay(&self, n_retries: i32) -> std::time::Duration {
+        let factor = 2u32.saturating_pow(n_retries.max(0).unsigned_abs());
+        std::time::Duration::from_millis(self.retry_base_delay_milliseconds).saturating_mul(factor)
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    /// How long a confirmation link stays valid after it was sent.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub confirmation_token_ttl_hours: i64,
+    /// How long signing up again while pending waits before another confirmation email goes out.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub confirmation_resend_cooldown_minutes: i64,
+    /// How long an unconfirmed subscription is kept before it is purged.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub pending_retention_days: i64,
+    /// How often the worker looks for abandoned subscriptions to purge.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub cleanup_interval_minutes: u64,
+}
+
+impl SubscriptionSettings {
+    pub fn confirmation_token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.confirmation_token_ttl_hours)
+    }
+
+    pub fn confirmation_resend_cooldown(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.confirmation_resend_cooldown_minutes)
+    }
+
+    pub fn pending_retention(&self) -> chrono::Duration {
+        chrono::Duration::days(self.pending_retention_days)
+    }
+
+    pub fn cleanup_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.cleanup_interval_minutes * 60)
     }
 }
@@ -0,0 +1,33 @@
+/// The state of a single issue delivery, as recorded in `issue_deliveries`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Queued,
+    Sent,
+    Failed,
+    SkippedInvalidAddress,
+    SkippedUnsubscribed,
+    /// The recipient was deleted after the delivery was enqueued.
+    SkippedMissingSubscriber,
+    /// Never sent because the issue was cancelled first.
+    Cancelled,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Queued => "queued",
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::SkippedInvalidAddress => "skipped_invalid_address",
+            DeliveryStatus::SkippedUnsubscribed => "skipped_unsubscribed",
+            DeliveryStatus::SkippedMissingSubscriber => "skipped_missing_subscriber",
+            DeliveryStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl AsRef<str> for DeliveryStatus {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
@@ -0,0 +1,225 @@
+use pulldown_cmark::{html, Event, Parser, Tag};
+
+use super::IssueTemplate;
+
+/// Both bodies of a newsletter issue, generated from a single Markdown source.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RenderedMarkdown {
+    /// Sanitized: scripts, event handlers and the like are stripped.
+    pub html: String,
+    pub text: String,
+}
+
+/// Render an issue authored in Markdown to HTML and plain text.
+///
+/// `{{ variable }}` placeholders are carried over untouched, including in link
+/// destinations, so that the bodies can still be personalised per recipient.
+pub fn render_markdown(markdown: &str) -> RenderedMarkdown {
+    let (markdown, placeholders) = protect_placeholders(markdown);
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new(&markdown));
+    let html = ammonia::clean(&unsafe_html);
+    let text = render_text(Parser::new(&markdown));
+
+    RenderedMarkdown {
+        html: restore_placeholders(html, &placeholders),
+        text: restore_placeholders(text, &placeholders),
+    }
+}
+
+/// Swap placeholders for plain words that Markdown leaves alone, e.g. `{{ unsubscribe_url }}`
+/// is not a valid link destination and its underscores could start an emphasis.
+///
+/// Only known template variables are protected: anything else between braces goes through
+/// sanitization like the rest of the body, and template validation reports it.
+fn protect_placeholders(markdown: &str) -> (String, Vec<(String, String)>) {
+    // A prefix the author did not type, so markers never collide with their text
+    let mut prefix = "zzplaceholder".to_string();
+    while markdown.contains(&prefix) {
+        prefix.push('z');
+    }
+    let mut protected = String::with_capacity(markdown.len());
+    let mut placeholders = Vec::new();
+    let mut rest = markdown;
+    while let Some(start) = rest.find("{{") {
+        // Unclosed placeholders are left in, for template validation to report
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + length + 2;
+        protected.push_str(&rest[..start]);
+        let placeholder = &rest[start..end];
+        if IssueTemplate::parse(placeholder).is_ok() {
+            let marker = format!("{prefix}{}zz", placeholders.len());
+            protected.push_str(&marker);
+            placeholders.push((marker, placeholder.to_string()));
+        } else {
+            protected.push_str(placeholder);
+        }
+        rest = &rest[end..];
+    }
+    protected.push_str(rest);
+
+    (protected, placeholders)
+}
+
+fn restore_placeholders(mut rendered: String, placeholders: &[(String, String)]) -> String {
+    for (marker, placeholder) in placeholders {
+        rendered = rendered.replace(marker, placeholder);
+    }
+
+    rendered
+}
+
+fn render_text<'a>(parser: impl Iterator<Item = Event<'a>>) -> String {
+    let mut text = String::new();
+    // The next number of each open list, `None` for bullet lists
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // Where the text of each open link, image or quote starts
+    let mut starts: Vec<usize> = Vec::new();
+    for event in parser {
+        match event {
+            Event::Start(Tag::Item) => {
+                text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(n)) => {
+                        text.push_str(&format!("{n}. "));
+                        *n += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::Start(Tag::List(first_number)) => {
+                if !lists.is_empty() {
+                    end_line(&mut text);
+                }
+                lists.push(first_number);
+            }
+            Event::Start(Tag::Link(..) | Tag::Image(..) | Tag::BlockQuote) => {
+                starts.push(text.len());
+            }
+            Event::Start(_) => {}
+            Event::End(Tag::Link(_, destination, _) | Tag::Image(_, destination, _)) => {
+                let start = starts.pop().unwrap_or_default();
+                if text[start..] != *destination {
+                    text.push_str(&format!(" ({destination})"));
+                }
+            }
+            Event::End(Tag::BlockQuote) => {
+                let start = starts.pop().unwrap_or_default();
+                let quoted = text.split_off(start);
+                for line in quoted.trim_end().lines() {
+                    text.push_str(format!("> {line}").trim_end());
+                    text.push('\n');
+                }
+                text.push('\n');
+            }
+            Event::End(Tag::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::End(Tag::Item) => end_line(&mut text),
+            Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::CodeBlock(_)) => {
+                end_line(&mut text);
+                if lists.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::End(_) => {}
+            Event::Text(s) | Event::Code(s) => text.push_str(&s),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => text.push_str("---\n\n"),
+            Event::TaskListMarker(checked) => text.push_str(if checked { "[x] " } else { "[ ] " }),
+            Event::Html(_) | Event::FootnoteReference(_) => {}
+        }
+    }
+    let trimmed_length = text.trim_end().len();
+    text.truncate(trimmed_length);
+
+    text
+}
+
+fn end_line(text: &mut String) {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_ok;
+
+    use super::render_markdown;
+    use crate::domain::IssueTemplate;
+
+    #[test]
+    fn paragraphs_and_emphasis_are_rendered() {
+        let rendered = render_markdown("# News\n\nHello *there*,\nfriend.\n\nBye");
+        assert_eq!(
+            rendered.html,
+            "<h1>News</h1>\n<p>Hello <em>there</em>,\nfriend.</p>\n<p>Bye</p>\n"
+        );
+        assert_eq!(rendered.text, "News\n\nHello there,\nfriend.\n\nBye");
+    }
+
+    #[test]
+    fn links_keep_their_destination_in_plain_text() {
+        let rendered =
+            render_markdown("Read [the docs](https://example.com/docs) or <https://example.com>.");
+        assert_eq!(
+            rendered.text,
+            "Read the docs (https://example.com/docs) or https://example.com."
+        );
+    }
+
+    #[test]
+    fn lists_and_quotes_are_laid_out_in_plain_text() {
+        let rendered = render_markdown("1. one\n2. two\n   - nested\n\n> quoted\n> text\n\nafter");
+        assert_eq!(
+            rendered.text,
+            "1. one\n2. two\n  - nested\n\n> quoted\n> text\n\nafter"
+        );
+    }
+
+    #[test]
+    fn html_is_sanitized() {
+        let rendered = render_markdown(
+            "<script>alert('hi')</script>\n\n<a href=\"https://example.com\" onclick=\"steal()\">x</a>",
+        );
+        assert!(!rendered.html.contains("script"));
+        assert!(!rendered.html.contains("onclick"));
+        assert!(rendered.html.contains(r#"href="https://example.com""#));
+    }
+
+    #[test]
+    fn placeholders_survive_rendering() {
+        let rendered =
+            render_markdown("Hi {{ subscriber.name }}!\n\n[Unsubscribe]({{ unsubscribe_url }})");
+        assert!(rendered.html.contains("<p>Hi {{ subscriber.name }}!</p>"));
+        assert!(rendered.html.contains(r#"href="{{ unsubscribe_url }}""#));
+        assert_eq!(
+            rendered.text,
+            "Hi {{ subscriber.name }}!\n\nUnsubscribe ({{ unsubscribe_url }})"
+        );
+        assert_ok!(IssueTemplate::parse(&rendered.html));
+        assert_ok!(IssueTemplate::parse(&rendered.text));
+    }
+
+    #[test]
+    fn markup_between_braces_is_sanitized() {
+        let rendered = render_markdown("{{ <script>alert('hi')</script> }}");
+        assert!(!rendered.html.contains("<script"));
+    }
+
+    #[test]
+    fn text_resembling_the_markers_is_left_alone() {
+        let rendered = render_markdown("zzplaceholder0zz {{ subscriber.name }}");
+        assert_eq!(
+            rendered.html,
+            "<p>zzplaceholder0zz {{ subscriber.name }}</p>\n"
+        );
+    }
+}
@@ -0,0 +1,32 @@
+/// The lifecycle state of a newsletter issue, as recorded in `newsletter_issues`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueStatus {
+    /// Being written; only ever sent as a test to its author.
+    Draft,
+    /// Waiting for `scheduled_for`; no delivery task exists yet.
+    Scheduled,
+    /// Delivery tasks have been enqueued.
+    Published,
+    /// Published, but workers leave its remaining delivery tasks alone until it is resumed.
+    Paused,
+    /// Cancelled before its send time, or while being delivered.
+    Cancelled,
+}
+
+impl IssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueStatus::Draft => "draft",
+            IssueStatus::Scheduled => "scheduled",
+            IssueStatus::Published => "published",
+            IssueStatus::Paused => "paused",
+            IssueStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl AsRef<str> for IssueStatus {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
@@ -0,0 +1,223 @@
+use htmlescape::encode_attribute;
+
+/// The body of a newsletter issue, with `{{ variable }}` placeholders filled in per recipient.
+///
+/// Supported variables are `subscriber.name`, `subscriber.email`, `unsubscribe_url` and
+/// `view_online_url`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct IssueTemplate(Vec<Segment>);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Variable(Variable),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    SubscriberName,
+    SubscriberEmail,
+    UnsubscribeUrl,
+    ViewOnlineUrl,
+}
+
+impl Variable {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "subscriber.name" => Some(Self::SubscriberName),
+            "subscriber.email" => Some(Self::SubscriberEmail),
+            "unsubscribe_url" => Some(Self::UnsubscribeUrl),
+            "view_online_url" => Some(Self::ViewOnlineUrl),
+            _ => None,
+        }
+    }
+}
+
+/// What a template is rendered with, for a single recipient.
+pub struct TemplateContext<'a> {
+    pub subscriber_name: &'a str,
+    pub subscriber_email: &'a str,
+    pub unsubscribe_url: &'a str,
+    /// Where the issue can be read in the public archive.
+    pub view_online_url: &'a str,
+}
+
+impl TemplateContext<'_> {
+    fn value(&self, variable: Variable) -> &str {
+        match variable {
+            Variable::SubscriberName => self.subscriber_name,
+            Variable::SubscriberEmail => self.subscriber_email,
+            Variable::UnsubscribeUrl => self.unsubscribe_url,
+            Variable::ViewOnlineUrl => self.view_online_url,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error("A `{{{{` at byte {0} is never closed with `}}}}`.")]
+    Unclosed(usize),
+    #[error("`{{{{ {0} }}}}` is not a known template variable.")]
+    UnknownVariable(String),
+}
+
+impl IssueTemplate {
+    pub fn parse(s: &str) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start + 2..].find("}}") else {
+                return Err(TemplateError::Unclosed(s.len() - rest.len() + start));
+            };
+            let name = rest[start + 2..start + 2 + length].trim();
+            let variable = Variable::parse(name)
+                .ok_or_else(|| TemplateError::UnknownVariable(name.to_string()))?;
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            segments.push(Segment::Variable(variable));
+            rest = &rest[start + 2 + length + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        Ok(Self(segments))
+    }
+
+    /// A template without placeholders, for content that predates templating.
+    pub fn verbatim(s: &str) -> Self {
+        Self(vec![Segment::Literal(s.to_string())])
+    }
+
+    /// Whether the template links to the issue in the public archive.
+    pub fn links_online_version(&self) -> bool {
+        self.0.contains(&Segment::Variable(Variable::ViewOnlineUrl))
+    }
+
+    pub fn render_text(&self, context: &TemplateContext) -> String {
+        self.render(context, |value| value.to_string())
+    }
+
+    /// Variables are escaped for use in attribute values, so a subscriber's name cannot inject
+    /// markup even where a placeholder sits in an unquoted attribute.
+    pub fn render_html(&self, context: &TemplateContext) -> String {
+        self.render(context, encode_attribute)
+    }
+
+    fn render(&self, context: &TemplateContext, encode: impl Fn(&str) -> String) -> String {
+        let mut rendered = String::new();
+        for segment in &self.0 {
+            match segment {
+                Segment::Literal(literal) => rendered.push_str(literal),
+                Segment::Variable(variable) => rendered.push_str(&encode(context.value(*variable))),
+            }
+        }
+
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err_eq, assert_ok};
+    use htmlescape::encode_attribute;
+
+    use super::{IssueTemplate, TemplateContext, TemplateError};
+
+    fn context() -> TemplateContext<'static> {
+        TemplateContext {
+            subscriber_name: "Ursula <Le Guin>",
+            subscriber_email: "ursula@example.com",
+            unsubscribe_url: "https://example.com/unsubscribe?a=1&b=2",
+            view_online_url: "https://example.com/issues/1",
+        }
+    }
+
+    #[test]
+    fn content_without_placeholders_is_rendered_verbatim() {
+        let template = assert_ok!(IssueTemplate::parse("<p>Hello!</p>"));
+        assert_eq!(template.render_html(&context()), "<p>Hello!</p>");
+    }
+
+    #[test]
+    fn variables_are_substituted_with_or_without_spaces() {
+        let template = assert_ok!(IssueTemplate::parse(
+            "Hi {{ subscriber.name }} ({{subscriber.email}}), bye: {{  unsubscribe_url }}"
+        ));
+        assert_eq!(
+            template.render_text(&context()),
+            "Hi Ursula <Le Guin> (ursula@example.com), bye: https://example.com/unsubscribe?a=1&b=2"
+        );
+    }
+
+    #[test]
+    fn variables_are_escaped_in_html() {
+        let template = assert_ok!(IssueTemplate::parse(
+            r#"<p>Hi {{ subscriber.name }}</p><a href="{{ unsubscribe_url }}">"#
+        ));
+        assert_eq!(
+            template.render_html(&context()),
+            r#"<p>Hi Ursula&#x20;&lt;Le&#x20;Guin&gt;</p><a href="https&#x3A;&#x2F;&#x2F;example&#x2E;com&#x2F;unsubscribe&#x3F;a&#x3D;1&amp;b&#x3D;2">"#
+        );
+    }
+
+    #[test]
+    fn variables_cannot_break_out_of_attributes() {
+        let context = TemplateContext {
+            subscriber_name: r#"x" onmouseover='alert(1)' y=z"#,
+            ..context()
+        };
+        for template in [
+            r#"<img alt="{{ subscriber.name }}">"#,
+            "<img alt='{{ subscriber.name }}'>",
+            "<img alt={{ subscriber.name }}>",
+        ] {
+            let rendered = assert_ok!(IssueTemplate::parse(template)).render_html(&context);
+            // Only the template's own quotes and spaces may delimit the attribute
+            let delimiters = |s: &str| s.matches(['"', '\'', ' ']).count();
+            assert_eq!(
+                delimiters(&rendered),
+                delimiters(&template.replace("{{ subscriber.name }}", "")),
+                "{rendered}"
+            );
+        }
+    }
+
+    #[test]
+    fn links_to_the_online_version_are_detected() {
+        let template = assert_ok!(IssueTemplate::parse("<a href=\"{{ view_online_url }}\">"));
+        assert!(template.links_online_version());
+        assert_eq!(
+            template.render_html(&context()),
+            format!(
+                "<a href=\"{}\">",
+                encode_attribute("https://example.com/issues/1")
+            )
+        );
+        let template = assert_ok!(IssueTemplate::parse("{{ unsubscribe_url }}"));
+        assert!(!template.links_online_version());
+    }
+
+    #[test]
+    fn unknown_variables_are_rejected() {
+        assert_err_eq!(
+            IssueTemplate::parse("Hi {{ subscriber.age }}"),
+            TemplateError::UnknownVariable("subscriber.age".into())
+        );
+    }
+
+    #[test]
+    fn unclosed_placeholders_are_rejected() {
+        assert_err_eq!(
+            IssueTemplate::parse("Hi {{ subscriber.name }}, {{ oops"),
+            TemplateError::Unclosed(26)
+        );
+    }
+
+    #[test]
+    fn verbatim_templates_keep_braces() {
+        let template = IssueTemplate::verbatim("{{ not a template");
+        assert_eq!(template.render_text(&context()), "{{ not a template");
+    }
+}
@@ -1,7 +1,19 @@
+mod delivery_status;
+mod issue_markdown;
+mod issue_status;
+mod issue_template;
 mod new_subscriber;
 mod subscriber_email;
 mod subscriber_name;
+mod subscription_status;
+mod unsubscribe_token;
 
+pub use delivery_status::DeliveryStatus;
+pub use issue_markdown::{render_markdown, RenderedMarkdown};
+pub use issue_status::IssueStatus;
+pub use issue_template::{IssueTemplate, TemplateContext, TemplateError};
 pub use new_subscriber::NewSubscriber;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscription_status::SubscriptionStatus;
+pub use unsubscribe_token::UnsubscribeToken;
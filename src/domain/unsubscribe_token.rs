@@ -0,0 +1,97 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+/// Per-subscriber token: the subscriber id followed by its HMAC-SHA256 tag, base64url-encoded.
+#[derive(Debug)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn generate(subscriber_id: Uuid, hmac_secret: &Secret<String>) -> Self {
+        let tag = mac(subscriber_id, hmac_secret).finalize().into_bytes();
+        let mut payload = subscriber_id.as_bytes().to_vec();
+        payload.extend_from_slice(&tag);
+
+        Self(URL_SAFE_NO_PAD.encode(payload))
+    }
+
+    /// Return the subscriber id the token was issued for, if the signature is valid.
+    pub fn verify(s: &str, hmac_secret: &Secret<String>) -> Result<Uuid, anyhow::Error> {
+        let payload = URL_SAFE_NO_PAD.decode(s)?;
+        if payload.len() <= 16 {
+            anyhow::bail!("The unsubscribe token is too short");
+        }
+        let (id, tag) = payload.split_at(16);
+        let subscriber_id = Uuid::from_slice(id)?;
+        mac(subscriber_id, hmac_secret).verify_slice(tag)?;
+
+        Ok(subscriber_id)
+    }
+}
+
+fn mac(subscriber_id: Uuid, hmac_secret: &Secret<String>) -> Hmac<sha2::Sha256> {
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(hmac_secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(b"unsubscribe:");
+    mac.update(subscriber_id.as_bytes());
+
+    mac
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for UnsubscribeToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use crate::domain::UnsubscribeToken;
+
+    fn secret() -> Secret<String> {
+        Secret::new("super-secret-key".to_string())
+    }
+
+    #[test]
+    fn a_generated_token_is_verified_successfully() {
+        let subscriber_id = Uuid::new_v4();
+        let token = UnsubscribeToken::generate(subscriber_id, &secret());
+        assert_ok_eq!(
+            UnsubscribeToken::verify(token.as_ref(), &secret()),
+            subscriber_id
+        );
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let token = UnsubscribeToken::generate(Uuid::new_v4(), &secret());
+        let other_secret = Secret::new("another-secret-key".to_string());
+        assert_err!(UnsubscribeToken::verify(token.as_ref(), &other_secret));
+    }
+
+    #[test]
+    fn a_tampered_token_is_rejected() {
+        let token = UnsubscribeToken::generate(Uuid::new_v4(), &secret()).to_string();
+        let mut tampered = token.into_bytes();
+        tampered[0] = if tampered[0] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert_err!(UnsubscribeToken::verify(&tampered, &secret()));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert_err!(UnsubscribeToken::verify("", &secret()));
+        assert_err!(UnsubscribeToken::verify("not-a-token!", &secret()));
+    }
+}
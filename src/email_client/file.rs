@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+
+use super::{smtp::build_message, Email, EmailTransport, SendOutcome};
+use crate::domain::SubscriberEmail;
+
+/// Writes every email to an `.eml` file in a directory, for local development.
+pub struct FileTransport {
+    mailer: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl FileTransport {
+    pub fn new(directory: impl Into<PathBuf>) -> Result<Self, anyhow::Error> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+
+        Ok(Self {
+            mailer: AsyncFileTransport::new(directory),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for FileTransport {
+    async fn send(&self, sender: &SubscriberEmail, email: &Email<'_>) -> SendOutcome {
+        let message = build_message(sender, email)?;
+        let id = self.mailer.send(message).await?;
+
+        Ok(Some(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_ok;
+    use uuid::Uuid;
+
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{EmailClient, FileTransport},
+    };
+
+    #[tokio::test]
+    async fn send_email_writes_an_eml_file() {
+        // Arrange
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let email_client = EmailClient::new(
+            SubscriberEmail::parse("sender@example.com".into()).unwrap(),
+            FileTransport::new(&directory).unwrap(),
+        );
+        let recipient = SubscriberEmail::parse("recipient@example.com".into()).unwrap();
+
+        // Act
+        let outcome = email_client
+            .send_email_with_headers(&recipient, "Hello", "<p>Hi!</p>", "Hi!", &[])
+            .await;
+
+        // Assert
+        assert_ok!(&outcome);
+        let id = outcome.unwrap().unwrap();
+        let eml = std::fs::read_to_string(directory.join(format!("{id}.eml"))).unwrap();
+        assert!(eml.contains("To: recipient@example.com"));
+        assert!(eml.contains("Subject: Hello"));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+mod file;
+mod postmark;
+mod smtp;
+
+pub use file::FileTransport;
+pub use postmark::PostmarkTransport;
+pub use smtp::SmtpTransport;
+
+use crate::domain::SubscriberEmail;
+
+/// Outcome of a single send: the message id assigned by the transport, if it reported one.
+pub type SendOutcome = Result<Option<String>, anyhow::Error>;
+
+/// A way of handing an outgoing email over for delivery.
+#[async_trait::async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, sender: &SubscriberEmail, email: &Email<'_>) -> SendOutcome;
+
+    /// Send several emails, returning one outcome per email in the same order.
+    ///
+    /// An `Err` means none of the emails went out: once some have been accepted, later failures
+    /// are reported per email. Transports without a bulk API send one by one.
+    async fn send_batch(
+        &self,
+        sender: &SubscriberEmail,
+        emails: &[Email<'_>],
+    ) -> Result<Vec<SendOutcome>, anyhow::Error> {
+        let mut outcomes = Vec::with_capacity(emails.len());
+        for email in emails {
+            outcomes.push(self.send(sender, email).await);
+        }
+
+        Ok(outcomes)
+    }
+}
+
+pub struct Email<'a> {
+    pub recipient: &'a SubscriberEmail,
+    pub subject: &'a str,
+    pub html_content: &'a str,
+    pub text_content: &'a str,
+    pub headers: &'a [EmailHeader],
+}
+
+/// A custom header attached to an outgoing email, e.g. `List-Unsubscribe`.
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader {
+    pub name: String,
+    pub value: String,
+}
+
+pub struct EmailClient {
+    sender: SubscriberEmail,
+    transport: Box<dyn EmailTransport>,
+}
+
+impl EmailClient {
+    pub fn new(sender: SubscriberEmail, transport: impl EmailTransport + 'static) -> Self {
+        Self {
+            sender,
+            transport: Box::new(transport),
+        }
+    }
+
+    pub async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<(), anyhow::Error> {
+        self.send_email_with_headers(recipient, subject, html_content, text_content, &[])
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the message id assigned by the transport, if it reported one.
+    pub async fn send_email_with_headers(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> SendOutcome {
+        let email = Email {
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            headers,
+        };
+
+        self.transport.send(&self.sender, &email).await
+    }
+
+    /// Returns one outcome per email, in the same order as `emails`.
+    pub async fn send_batch(
+        &self,
+        emails: &[Email<'_>],
+    ) -> Result<Vec<SendOutcome>, anyhow::Error> {
+        if emails.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.transport.send_batch(&self.sender, emails).await
+    }
+}
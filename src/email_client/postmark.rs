@@ -0,0 +1,488 @@
+use anyhow::Context;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+
+use super::{Email, EmailHeader, EmailTransport, SendOutcome};
+use crate::domain::SubscriberEmail;
+
+/// Delivers emails through Postmark's JSON API.
+pub struct PostmarkTransport {
+    http_client: reqwest::Client,
+    base_url: String,
+    authorization_token: Secret<String>,
+}
+
+impl PostmarkTransport {
+    pub fn new(
+        base_url: String,
+        authorization_token: Secret<String>,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = reqwest::Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            http_client,
+            base_url,
+            authorization_token,
+        }
+    }
+}
+
+/// Postmark accepts at most this many messages per batch request.
+const MAX_BATCH_SIZE: usize = 500;
+
+#[async_trait::async_trait]
+impl EmailTransport for PostmarkTransport {
+    async fn send(&self, sender: &SubscriberEmail, email: &Email<'_>) -> SendOutcome {
+        let url = Url::parse(&self.base_url)?.join("email")?;
+        let response = self
+            .http_client
+            .post(url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&SendEmailRequest::new(sender, email))
+            .send()
+            .await?
+            .error_for_status()?;
+        let message_id = response
+            .json::<SendEmailResponse>()
+            .await
+            .ok()
+            .map(|r| r.message_id);
+
+        Ok(message_id)
+    }
+
+    async fn send_batch(
+        &self,
+        sender: &SubscriberEmail,
+        emails: &[Email<'_>],
+    ) -> Result<Vec<SendOutcome>, anyhow::Error> {
+        // No point in paying for the batch endpoint's per-message bookkeeping for a single email
+        if let [email] = emails {
+            return Ok(vec![self.send(sender, email).await]);
+        }
+        let url = Url::parse(&self.base_url)?.join("email/batch")?;
+        let mut outcomes = Vec::with_capacity(emails.len());
+        for chunk in emails.chunks(MAX_BATCH_SIZE) {
+            match self.send_chunk(&url, sender, chunk).await {
+                Ok(results) => outcomes.extend(results),
+                // Nothing went out yet: the batch was rejected as a whole
+                Err(e) if outcomes.is_empty() => return Err(e),
+                // Earlier chunks were accepted, so only this one's emails have failed
+                Err(e) => outcomes.extend(chunk.iter().map(|_| Err(anyhow::anyhow!("{e:#}")))),
+            }
+        }
+
+        Ok(outcomes)
+    }
+}
+
+impl PostmarkTransport {
+    /// Send up to `MAX_BATCH_SIZE` emails in a single request to the batch endpoint.
+    async fn send_chunk(
+        &self,
+        url: &Url,
+        sender: &SubscriberEmail,
+        chunk: &[Email<'_>],
+    ) -> Result<Vec<SendOutcome>, anyhow::Error> {
+        let request_body: Vec<_> = chunk
+            .iter()
+            .map(|email| SendEmailRequest::new(sender, email))
+            .collect();
+        let results = self
+            .http_client
+            .post(url.clone())
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Vec<BatchResult>>()
+            .await
+            .context("Failed to parse Postmark's batch response")?;
+        if results.len() != chunk.len() {
+            anyhow::bail!(
+                "Postmark returned {} results for a batch of {} emails",
+                results.len(),
+                chunk.len()
+            );
+        }
+
+        Ok(results.into_iter().map(BatchResult::into_outcome).collect())
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SendEmailResponse {
+    #[serde(rename = "MessageID")]
+    message_id: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BatchResult {
+    error_code: i64,
+    message: String,
+    #[serde(rename = "MessageID")]
+    message_id: Option<String>,
+}
+
+impl BatchResult {
+    fn into_outcome(self) -> SendOutcome {
+        if self.error_code != 0 {
+            anyhow::bail!("Postmark error {}: {}", self.error_code, self.message);
+        }
+
+        Ok(self.message_id)
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    headers: &'a [EmailHeader],
+}
+
+impl<'a> SendEmailRequest<'a> {
+    fn new(sender: &'a SubscriberEmail, email: &'a Email<'a>) -> Self {
+        Self {
+            from: sender.as_ref(),
+            to: email.recipient.as_ref(),
+            subject: email.subject,
+            html_body: email.html_content,
+            text_body: email.text_content,
+            headers: email.headers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+    use fake::{
+        faker::{
+            internet::en::SafeEmail,
+            lorem::en::{Paragraph, Sentence},
+        },
+        Fake, Faker,
+    };
+    use secrecy::Secret;
+    use wiremock::{
+        matchers::{any, header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{Email, EmailClient, EmailHeader, PostmarkTransport},
+    };
+
+    use super::MAX_BATCH_SIZE;
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            let body = match result {
+                Ok(b) => b,
+                Err(_) => return false,
+            };
+            ["From", "To", "Subject", "HtmlBody", "TextBody"]
+                .into_iter()
+                .all(|field_name| body.get(field_name).is_some())
+        }
+    }
+
+    struct HeadersMatcher;
+
+    impl wiremock::Match for HeadersMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let body: serde_json::Value = match serde_json::from_slice(&request.body) {
+                Ok(b) => b,
+                Err(_) => return false,
+            };
+            body["Headers"]
+                == serde_json::json!([{ "Name": "List-Unsubscribe", "Value": "<https://example.com>" }])
+        }
+    }
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn email_client(base_url: String) -> EmailClient {
+        EmailClient::new(
+            email(),
+            PostmarkTransport::new(
+                base_url,
+                Secret::new(Faker.fake()),
+                std::time::Duration::from_millis(200),
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_fires_a_request_to_base_url() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let response = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(response);
+    }
+
+    #[tokio::test]
+    async fn send_email_with_headers_includes_them_in_the_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let headers = [EmailHeader {
+            name: "List-Unsubscribe".into(),
+            value: "<https://example.com>".into(),
+        }];
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .and(HeadersMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let response = email_client
+            .send_email_with_headers(&email(), &subject(), &content(), &content(), &headers)
+            .await;
+
+        // Assert
+        assert_ok!(response);
+    }
+
+    #[tokio::test]
+    async fn send_email_with_headers_returns_the_provider_message_id() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "To": "receiver@example.com",
+                "SubmittedAt": "2023-11-04T02:19:07.0000000-05:00",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+                "ErrorCode": 0,
+                "Message": "OK"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let response = email_client
+            .send_email_with_headers(&email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        // Assert
+        assert_ok_eq!(
+            response,
+            Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn send_batch_reports_an_outcome_per_message() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let (recipients, subject, content) = ([email(), email()], subject(), content());
+        let emails: Vec<_> = recipients
+            .iter()
+            .map(|recipient| Email {
+                recipient,
+                subject: &subject,
+                html_content: &content,
+                text_content: &content,
+                headers: &[],
+            })
+            .collect();
+
+        Mock::given(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {
+                    "ErrorCode": 0,
+                    "Message": "OK",
+                    "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817"
+                },
+                {
+                    "ErrorCode": 406,
+                    "Message": "You tried to send to a recipient that has been marked as inactive."
+                }
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcomes = email_client.send_batch(&emails).await.unwrap();
+
+        // Assert
+        assert_eq!(outcomes.len(), 2);
+        assert_ok_eq!(
+            &outcomes[0],
+            &Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817".to_string())
+        );
+        assert_err!(&outcomes[1]);
+    }
+
+    #[tokio::test]
+    async fn send_batch_fails_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let (recipients, subject, content) = ([email(), email()], subject(), content());
+        let emails: Vec<_> = recipients
+            .iter()
+            .map(|recipient| Email {
+                recipient,
+                subject: &subject,
+                html_content: &content,
+                text_content: &content,
+                headers: &[],
+            })
+            .collect();
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_batch(&emails).await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_batch_only_fails_the_emails_of_a_rejected_chunk() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipients: Vec<_> = (0..MAX_BATCH_SIZE + 1).map(|_| email()).collect();
+        let (subject, content) = (subject(), content());
+        let emails: Vec<_> = recipients
+            .iter()
+            .map(|recipient| Email {
+                recipient,
+                subject: &subject,
+                html_content: &content,
+                text_content: &content,
+                headers: &[],
+            })
+            .collect();
+        let accepted_chunk: Vec<_> = (0..MAX_BATCH_SIZE)
+            .map(|_| serde_json::json!({ "ErrorCode": 0, "Message": "OK", "MessageID": "id" }))
+            .collect();
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(accepted_chunk))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcomes = email_client.send_batch(&emails).await.unwrap();
+
+        // Assert
+        assert_eq!(outcomes.len(), MAX_BATCH_SIZE + 1);
+        assert!(outcomes[..MAX_BATCH_SIZE].iter().all(Result::is_ok));
+        assert_err!(&outcomes[MAX_BATCH_SIZE]);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let response = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(response);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_takes_too_long() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        let delayed_response =
+            ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(delayed_response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let response = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(response);
+    }
+}
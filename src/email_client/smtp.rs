@@ -0,0 +1,202 @@
+use lettre::{
+    message::{
+        header::{HeaderName, HeaderValue},
+        Mailbox, MultiPart,
+    },
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::{ExposeSecret, Secret};
+
+use super::{Email, EmailTransport, SendOutcome};
+use crate::domain::SubscriberEmail;
+
+/// Delivers emails to an SMTP relay.
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Option<(String, Secret<String>)>,
+        require_tls: bool,
+        timeout: std::time::Duration,
+    ) -> Result<Self, anyhow::Error> {
+        let mut builder = if require_tls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        }
+        .port(port)
+        .timeout(Some(timeout));
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(
+                username,
+                password.expose_secret().to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            mailer: builder.build(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SmtpTransport {
+    async fn send(&self, sender: &SubscriberEmail, email: &Email<'_>) -> SendOutcome {
+        let message = build_message(sender, email)?;
+        let message_id = message.headers().get_raw("Message-ID").map(str::to_owned);
+        self.mailer.send(message).await?;
+
+        Ok(message_id)
+    }
+}
+
+/// Render an email as a multipart/alternative MIME message.
+pub(super) fn build_message(
+    sender: &SubscriberEmail,
+    email: &Email<'_>,
+) -> Result<Message, anyhow::Error> {
+    let mut builder = Message::builder()
+        .from(sender.as_ref().parse::<Mailbox>()?)
+        .to(email.recipient.as_ref().parse::<Mailbox>()?)
+        .subject(email.subject)
+        .message_id(None);
+    for header in email.headers {
+        let name = HeaderName::new_from_ascii(header.name.clone())?;
+        builder = builder.raw_header(HeaderValue::new(name, header.value.clone()));
+    }
+    let message = builder.multipart(MultiPart::alternative_plain_html(
+        email.text_content.to_owned(),
+        email.html_content.to_owned(),
+    ))?;
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        sync::oneshot,
+    };
+
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{EmailClient, EmailHeader, SmtpTransport},
+    };
+
+    /// Accept a single SMTP session and hand back the raw DATA payload.
+    async fn smtp_sink() -> (u16, oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            let mut data = String::new();
+            let mut in_data = false;
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if in_data {
+                    if line == "." {
+                        in_data = false;
+                        writer.write_all(b"250 OK\r\n").await.unwrap();
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    continue;
+                }
+                let command = line.to_uppercase();
+                let reply: &[u8] = if command.starts_with("EHLO") {
+                    b"250 localhost\r\n"
+                } else if command.starts_with("DATA") {
+                    in_data = true;
+                    b"354 Go ahead\r\n"
+                } else if command.starts_with("QUIT") {
+                    writer.write_all(b"221 Bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            let _ = tx.send(data);
+        });
+
+        (port, rx)
+    }
+
+    fn email_client(port: u16) -> EmailClient {
+        let transport = SmtpTransport::new(
+            "127.0.0.1",
+            port,
+            None,
+            false,
+            std::time::Duration::from_secs(1),
+        )
+        .unwrap();
+
+        EmailClient::new(
+            SubscriberEmail::parse("sender@example.com".into()).unwrap(),
+            transport,
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_a_message_to_the_smtp_server() {
+        // Arrange
+        let (port, data) = smtp_sink().await;
+        let email_client = email_client(port);
+        let recipient = SubscriberEmail::parse("recipient@example.com".into()).unwrap();
+        let headers = [EmailHeader {
+            name: "List-Unsubscribe".into(),
+            value: "<https://example.com>".into(),
+        }];
+
+        // Act
+        let outcome = email_client
+            .send_email_with_headers(
+                &recipient,
+                "Newsletter title",
+                "<p>Newsletter body as HTML</p>",
+                "Newsletter body as plain text.",
+                &headers,
+            )
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        let data = data.await.unwrap();
+        assert!(data.contains("To: recipient@example.com"));
+        assert!(data.contains("Subject: Newsletter title"));
+        assert!(data.contains("List-Unsubscribe: <https://example.com>"));
+        assert!(data.contains("Newsletter body as plain text."));
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_is_unreachable() {
+        // Arrange
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let email_client = email_client(port);
+        let recipient = SubscriberEmail::parse("recipient@example.com".into()).unwrap();
+
+        // Act
+        let outcome = email_client
+            .send_email(&recipient, "Subject", "<p>Body</p>", "Body")
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+}
@@ -0,0 +1,355 @@
+use std::time::Duration;
+
+use secrecy::Secret;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::Span;
+use uuid::Uuid;
+
+use crate::{
+    configuration::WorkerSettings,
+    domain::SubscriberEmail,
+    email_client::{Email, EmailClient, EmailHeader},
+    issue_delivery_worker::{notify_workers, send_within_rate_limit, Attempt, ExecutionOutcome},
+    rate_limiter::RateLimiter,
+    welcome_email::{render_welcome_email, WelcomeEmail},
+};
+
+/// Store a transactional email, e.g. a confirmation link, to be sent by the workers once
+/// `transaction` commits.
+///
+/// Nothing is sent if the transaction is rolled back, and a slow or failing email provider
+/// never holds up the request that triggered the email.
+#[tracing::instrument(skip_all, fields(subject = %email.subject))]
+pub async fn enqueue_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &Email<'_>,
+) -> Result<(), sqlx::Error> {
+    let (header_names, header_values) = header_columns(email.headers);
+    sqlx::query!(
+        r#"
+        INSERT INTO email_outbox (
+            id,
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            header_names,
+            header_values
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        email.recipient.as_ref(),
+        email.subject,
+        email.html_content,
+        email.text_content,
+        &header_names,
+        &header_values
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    notify_workers(transaction).await?;
+
+    Ok(())
+}
+
+/// Headers are stored as two parallel arrays of names and values.
+fn header_columns(headers: &[EmailHeader]) -> (Vec<String>, Vec<String>) {
+    headers
+        .iter()
+        .map(|h| (h.name.clone(), h.value.clone()))
+        .unzip()
+}
+
+/// A row of `email_outbox`, locked by the current transaction.
+struct Task {
+    id: Uuid,
+    recipient: String,
+    subject: String,
+    html_content: String,
+    text_content: String,
+    header_names: Vec<String>,
+    header_values: Vec<String>,
+    n_retries: i32,
+    welcome_subscriber_id: Option<Uuid>,
+}
+
+/// A task whose recipient checks out, ready to be handed over to the email client.
+struct PendingEmail {
+    task: Task,
+    recipient: SubscriberEmail,
+    headers: Vec<EmailHeader>,
+}
+
+#[tracing::instrument(skip_all, fields(n_tasks = tracing::field::Empty), err)]
+pub async fn try_send_outbox_emails(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+    settings: &WorkerSettings,
+    rate_limiter: &RateLimiter,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let tasks = dequeue_tasks(&mut transaction, settings.batch_size).await?;
+    if tasks.is_empty() {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    }
+    Span::current().record("n_tasks", tasks.len());
+
+    let mut pending = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let task = match task.welcome_subscriber_id {
+            Some(subscriber_id) => {
+                match render_welcome_email(&mut transaction, subscriber_id, base_url, hmac_secret)
+                    .await?
+                {
+                    Some(email) => store_rendered_email(&mut transaction, task, email).await?,
+                    None => {
+                        tracing::info!(
+                            email_id = %task.id,
+                            "Dropping a welcome email. Welcome emails have been disabled or \
+                            the subscriber is no longer confirmed."
+                        );
+                        delete_task(&mut transaction, &task).await?;
+                        continue;
+                    }
+                }
+            }
+            None => task,
+        };
+        let recipient = match SubscriberEmail::parse(task.recipient.clone()) {
+            Ok(recipient) => recipient,
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    email_id = %task.id,
+                    "Dropping a transactional email. Its recipient is invalid"
+                );
+                delete_task(&mut transaction, &task).await?;
+                continue;
+            }
+        };
+        let headers = task
+            .header_names
+            .iter()
+            .zip(&task.header_values)
+            .map(|(name, value)| EmailHeader {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect();
+        pending.push(PendingEmail {
+            recipient,
+            headers,
+            task,
+        });
+    }
+
+    let emails: Vec<Email> = pending
+        .iter()
+        .map(|p| Email {
+            recipient: &p.recipient,
+            subject: &p.task.subject,
+            html_content: &p.task.html_content,
+            text_content: &p.task.text_content,
+            headers: &p.headers,
+        })
+        .collect();
+    let outcomes = send_within_rate_limit(email_client, rate_limiter, &emails).await;
+
+    for (p, outcome) in pending.iter().zip(outcomes) {
+        let task = &p.task;
+        match Attempt::new(outcome, task.n_retries, settings) {
+            Attempt::Sent(_) => delete_task(&mut transaction, task).await?,
+            Attempt::Exhausted(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    email_id = %task.id,
+                    subject = %task.subject,
+                    "Failed to send a transactional email. \
+                    Retries exhausted, moving it to the dead-letter table."
+                );
+                dead_letter_task(&mut transaction, task, &e).await?;
+            }
+            Attempt::RetryLater(e, delay) => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    email_id = %task.id,
+                    subject = %task.subject,
+                    "Failed to send a transactional email. Retrying later."
+                );
+                retry_task_later(&mut transaction, task, delay).await?;
+            }
+        }
+    }
+    transaction.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+/// Fill in a welcome email with the content it is about to be sent with, so that dead letters
+/// show what was actually attempted.
+#[tracing::instrument(skip_all)]
+async fn store_rendered_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    task: Task,
+    email: WelcomeEmail,
+) -> Result<Task, sqlx::Error> {
+    let (header_names, header_values) = header_columns(&email.headers);
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET
+            recipient = $2,
+            subject = $3,
+            html_content = $4,
+            text_content = $5,
+            header_names = $6,
+            header_values = $7
+        WHERE id = $1
+        "#,
+        task.id,
+        email.recipient.as_ref(),
+        email.subject,
+        email.html_content,
+        email.text_content,
+        &header_names,
+        &header_values
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(Task {
+        recipient: email.recipient.as_ref().to_owned(),
+        subject: email.subject,
+        html_content: email.html_content,
+        text_content: email.text_content,
+        header_names,
+        header_values,
+        ..task
+    })
+}
+
+/// Lock up to `batch_size` due emails, oldest first.
+#[tracing::instrument(skip_all)]
+async fn dequeue_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    batch_size: i64,
+) -> Result<Vec<Task>, sqlx::Error> {
+    sqlx::query_as!(
+        Task,
+        r#"
+        SELECT
+            id,
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            header_names,
+            header_values,
+            n_retries,
+            welcome_subscriber_id
+        FROM email_outbox
+        WHERE execute_after <= now()
+        ORDER BY created_at
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT $1
+        "#,
+        batch_size
+    )
+    .fetch_all(transaction.as_mut())
+    .await
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    transaction: &mut Transaction<'_, Postgres>,
+    task: &Task,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM email_outbox
+        WHERE id = $1
+        "#,
+        task.id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn retry_task_later(
+    transaction: &mut Transaction<'_, Postgres>,
+    task: &Task,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    let delay_milliseconds = i64::try_from(delay.as_millis())?;
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET
+            n_retries = n_retries + 1,
+            execute_after = now() + $2 * interval '1 millisecond'
+        WHERE id = $1
+        "#,
+        task.id,
+        delay_milliseconds as f64
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn dead_letter_task(
+    transaction: &mut Transaction<'_, Postgres>,
+    task: &Task,
+    error: &anyhow::Error,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_outbox_dead_letters (
+            id,
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            header_names,
+            header_values,
+            n_retries,
+            last_error,
+            created_at,
+            failed_at,
+            welcome_subscriber_id
+        )
+        SELECT
+            id,
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            header_names,
+            header_values,
+            n_retries,
+            $2,
+            created_at,
+            now(),
+            welcome_subscriber_id
+        FROM email_outbox
+        WHERE id = $1
+        "#,
+        task.id,
+        error.to_string()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    delete_task(transaction, task).await
+}
@@ -1,12 +1,29 @@
-use std::time::Duration;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+    time::Duration,
+};
 
-use sqlx::{PgPool, Postgres, Transaction};
-use tracing::{field::display, Span};
+use chrono::Utc;
+use secrecy::Secret;
+use sqlx::{postgres::PgListener, PgExecutor, PgPool, Postgres, Transaction};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::Span;
 use uuid::Uuid;
 
 use crate::{
-    configuration::Settings, domain::SubscriberEmail, email_client::EmailClient,
-    startup::get_connection_pool,
+    configuration::{Settings, WorkerSettings},
+    domain::{
+        DeliveryStatus, IssueStatus, IssueTemplate, SubscriberEmail, SubscriptionStatus,
+        TemplateContext, UnsubscribeToken,
+    },
+    email_client::{Email, EmailClient, EmailHeader, SendOutcome},
+    email_outbox::try_send_outbox_emails,
+    issue_scheduler::{next_scheduled_send, publish_due_issues},
+    rate_limiter::RateLimiter,
+    startup::get_connection_pool_with_size,
+    subscription_cleanup::cleanup_loop,
 };
 
 struct NewsletterIssue {
@@ -16,7 +33,10 @@ struct NewsletterIssue {
 }
 
 #[tracing::instrument(skip_all)]
-async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
+async fn get_issue(
+    executor: impl PgExecutor<'_>,
+    issue_id: Uuid,
+) -> Result<NewsletterIssue, anyhow::Error> {
     let issue = sqlx::query_as!(
         NewsletterIssue,
         r#"
@@ -27,105 +47,440 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
         "#,
         issue_id
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await?;
 
     Ok(issue)
 }
 
+/// An issue's bodies, parsed once per batch and rendered for each recipient.
+struct IssueTemplates {
+    title: String,
+    html: IssueTemplate,
+    text: IssueTemplate,
+}
+
+impl From<NewsletterIssue> for IssueTemplates {
+    fn from(issue: NewsletterIssue) -> Self {
+        // Templates are validated at publish time: content that still fails to parse
+        // predates templating, and is sent as it was written.
+        let parse = |content: &str| {
+            IssueTemplate::parse(content).unwrap_or_else(|_| IssueTemplate::verbatim(content))
+        };
+        Self {
+            html: parse(&issue.html_content),
+            text: parse(&issue.text_content),
+            title: issue.title,
+        }
+    }
+}
+
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
 }
 
-#[tracing::instrument(
-    skip_all,
-    fields(
-        newsletter_issue_id=tracing::field::Empty,
-        subscriber_email=tracing::field::Empty
-    ),
-    err
-)]
+/// Hand a batch of emails over to the email client once the rate limit allows it.
+///
+/// Returns one outcome per email: if the batch is rejected as a whole, every email in it
+/// failed for the same reason.
+pub(crate) async fn send_within_rate_limit(
+    email_client: &EmailClient,
+    rate_limiter: &RateLimiter,
+    emails: &[Email<'_>],
+) -> Vec<SendOutcome> {
+    rate_limiter.acquire(emails.len()).await;
+    match email_client.send_batch(emails).await {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            let message = format!("{e:#}");
+            emails
+                .iter()
+                .map(|_| Err(anyhow::anyhow!(message.clone())))
+                .collect()
+        }
+    }
+}
+
+/// What becomes of a queued email after an attempt to send it.
+pub(crate) enum Attempt {
+    /// Sent, with the message id assigned by the email provider, if any.
+    Sent(Option<String>),
+    /// Failed: try again once the backoff delay has elapsed.
+    RetryLater(anyhow::Error, Duration),
+    /// Failed with the retry budget exhausted.
+    Exhausted(anyhow::Error),
+}
+
+impl Attempt {
+    pub(crate) fn new(outcome: SendOutcome, n_retries: i32, settings: &WorkerSettings) -> Self {
+        match outcome {
+            Ok(message_id) => Attempt::Sent(message_id),
+            Err(e) if n_retries >= settings.max_retries => Attempt::Exhausted(e),
+            Err(e) => Attempt::RetryLater(e, settings.retry_delay(n_retries)),
+        }
+    }
+}
+
+/// A row of `issue_delivery_queue`, locked by the current transaction.
+struct Task {
+    issue_id: Uuid,
+    email: String,
+    n_retries: i32,
+}
+
+/// A task whose recipient checks out, ready to be handed over to the email client.
+struct PendingEmail {
+    task: Task,
+    recipient: SubscriberEmail,
+    html_content: String,
+    text_content: String,
+    headers: Vec<EmailHeader>,
+}
+
+#[tracing::instrument(skip_all, fields(n_tasks = tracing::field::Empty), err)]
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+    settings: &WorkerSettings,
+    rate_limiter: &RateLimiter,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
-    let (transaction, issue_id, email) = match dequeue_task(pool).await? {
-        Some(t) => t,
-        None => {
-            return Ok(ExecutionOutcome::EmptyQueue);
-        }
-    };
-    Span::current()
-        .record("newsletter_issue_id", &display(issue_id))
-        .record("subscriber_email", &display(&email));
-    match SubscriberEmail::parse(email.clone()) {
-        Ok(email) => {
-            let issue = get_issue(pool, issue_id).await?;
-            if let Err(e) = email_client
-                .send_email(
-                    &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
-                )
-                .await
-            {
+    let (mut transaction, tasks) = dequeue_tasks(pool, settings.batch_size).await?;
+    if tasks.is_empty() {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    }
+    Span::current().record("n_tasks", tasks.len());
+
+    let mut issues = HashMap::new();
+    let mut pending = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let recipient = match SubscriberEmail::parse(task.email.clone()) {
+            Ok(recipient) => recipient,
+            Err(e) => {
                 tracing::error!(
                     error.cause_chain = ?e,
                     error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber. Skipping."
+                    newsletter_issue_id = %task.issue_id,
+                    subscriber_email = %task.email,
+                    "Skipping a confirmed subscriber. Their stored contact details are invalid"
                 );
+                let delivery = Delivery::new(DeliveryStatus::SkippedInvalidAddress);
+                record_delivery(&mut transaction, &task, &delivery).await?;
+                delete_task(&mut transaction, &task).await?;
+                continue;
+            }
+        };
+        let subscriber = match get_subscriber(transaction.as_mut(), recipient.as_ref()).await? {
+            Some(subscriber) if subscriber.status == SubscriptionStatus::Confirmed.as_str() => {
+                subscriber
+            }
+            Some(_) => {
+                tracing::info!(
+                    newsletter_issue_id = %task.issue_id,
+                    subscriber_email = %task.email,
+                    "Skipping a subscriber that is no longer confirmed."
+                );
+                let delivery = Delivery::new(DeliveryStatus::SkippedUnsubscribed);
+                record_delivery(&mut transaction, &task, &delivery).await?;
+                delete_task(&mut transaction, &task).await?;
+                continue;
+            }
+            None => {
+                tracing::warn!(
+                    newsletter_issue_id = %task.issue_id,
+                    subscriber_email = %task.email,
+                    "Skipping a subscriber that no longer exists."
+                );
+                let delivery = Delivery::new(DeliveryStatus::SkippedMissingSubscriber);
+                record_delivery(&mut transaction, &task, &delivery).await?;
+                delete_task(&mut transaction, &task).await?;
+                continue;
+            }
+        };
+        let issue = match issues.entry(task.issue_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(IssueTemplates::from(
+                get_issue(transaction.as_mut(), task.issue_id).await?,
+            )),
+        };
+        let unsubscribe_token = UnsubscribeToken::generate(subscriber.id, hmac_secret);
+        let unsubscribe_url = unsubscribe_url(base_url, &unsubscribe_token);
+        let view_online_url = view_online_url(base_url, task.issue_id);
+        let context = TemplateContext {
+            subscriber_name: &subscriber.name,
+            subscriber_email: recipient.as_ref(),
+            unsubscribe_url: &unsubscribe_url,
+            view_online_url: &view_online_url,
+        };
+        pending.push(PendingEmail {
+            html_content: issue.html.render_html(&context),
+            text_content: issue.text.render_text(&context),
+            headers: list_unsubscribe_headers(&unsubscribe_url),
+            recipient,
+            task,
+        });
+    }
+
+    let emails: Vec<Email> = pending
+        .iter()
+        .map(|p| {
+            let issue = &issues[&p.task.issue_id];
+            Email {
+                recipient: &p.recipient,
+                subject: &issue.title,
+                html_content: &p.html_content,
+                text_content: &p.text_content,
+                headers: &p.headers,
+            }
+        })
+        .collect();
+    let outcomes = send_within_rate_limit(email_client, rate_limiter, &emails).await;
+
+    for (p, outcome) in pending.iter().zip(outcomes) {
+        let task = &p.task;
+        match Attempt::new(outcome, task.n_retries, settings) {
+            Attempt::Sent(message_id) => {
+                let delivery = Delivery {
+                    provider_message_id: message_id,
+                    ..Delivery::new(DeliveryStatus::Sent)
+                };
+                record_delivery(&mut transaction, task, &delivery).await?;
+                delete_task(&mut transaction, task).await?;
+            }
+            Attempt::Exhausted(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    newsletter_issue_id = %task.issue_id,
+                    subscriber_email = %task.email,
+                    "Failed to deliver issue to a confirmed subscriber. \
+                    Retries exhausted, moving it to the dead-letter table."
+                );
+                let delivery = Delivery {
+                    last_error: Some(e.to_string()),
+                    ..Delivery::new(DeliveryStatus::Failed)
+                };
+                record_delivery(&mut transaction, task, &delivery).await?;
+                dead_letter_task(&mut transaction, task, &e).await?;
+            }
+            Attempt::RetryLater(e, delay) => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    newsletter_issue_id = %task.issue_id,
+                    subscriber_email = %task.email,
+                    "Failed to deliver issue to a confirmed subscriber. Retrying later."
+                );
+                let delivery = Delivery {
+                    last_error: Some(e.to_string()),
+                    ..Delivery::new(DeliveryStatus::Queued)
+                };
+                record_delivery(&mut transaction, task, &delivery).await?;
+                retry_task_later(&mut transaction, task, delay).await?;
             }
-        }
-        Err(e) => {
-            tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "Skipping a confirmed subscriber. Their stored contact details are invalid"
-            );
         }
     }
-    delete_task(transaction, issue_id, &email).await?;
+    transaction.commit().await?;
 
     Ok(ExecutionOutcome::TaskCompleted)
 }
 
+/// The outcome of a delivery attempt, to be stored in `issue_deliveries`.
+struct Delivery {
+    status: DeliveryStatus,
+    provider_message_id: Option<String>,
+    last_error: Option<String>,
+}
+
+impl Delivery {
+    fn new(status: DeliveryStatus) -> Self {
+        Self {
+            status,
+            provider_message_id: None,
+            last_error: None,
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn record_delivery(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    delivery: &Delivery,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE issue_deliveries
+        SET
+            status = $3,
+            provider_message_id = COALESCE($4, provider_message_id),
+            last_error = $5,
+            updated_at = now()
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        task.issue_id,
+        task.email,
+        delivery.status.as_str(),
+        delivery.provider_message_id,
+        delivery.last_error
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) fn unsubscribe_url(base_url: &str, token: &UnsubscribeToken) -> String {
+    format!("{base_url}/subscriptions/unsubscribe?unsubscribe_token={token}")
+}
+
+/// Where an issue can be read in the public web archive.
+pub fn view_online_url(base_url: &str, issue_id: Uuid) -> String {
+    format!("{base_url}/issues/{issue_id}")
+}
+
+/// RFC 8058 one-click unsubscribe headers for a single recipient.
+pub(crate) fn list_unsubscribe_headers(unsubscribe_url: &str) -> Vec<EmailHeader> {
+    vec![
+        EmailHeader {
+            name: "List-Unsubscribe".into(),
+            value: format!("<{unsubscribe_url}>"),
+        },
+        EmailHeader {
+            name: "List-Unsubscribe-Post".into(),
+            value: "List-Unsubscribe=One-Click".into(),
+        },
+    ]
+}
+
+struct Subscriber {
+    id: Uuid,
+    name: String,
+    status: String,
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_subscriber(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, name, status
+        FROM subscriptions
+        WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(subscriber)
+}
+
 type PgTransaction = Transaction<'static, Postgres>;
 
+/// The channel idle workers `LISTEN` on to learn that new deliveries have been enqueued,
+/// or that an issue has been scheduled.
+pub const DELIVERY_QUEUE_CHANNEL: &str = "issue_delivery_queue";
+
+/// Wake up idle workers once `transaction` commits.
+///
+/// Postgres holds back notifications until the surrounding transaction commits,
+/// so workers never wake up to find the new tasks still invisible.
+#[tracing::instrument(skip_all)]
+pub async fn notify_workers(
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("SELECT pg_notify($1, '')", DELIVERY_QUEUE_CHANNEL)
+        .execute(transaction.as_mut())
+        .await?;
+
+    Ok(())
+}
+
+/// Fan out one delivery task per confirmed subscriber and wake up the workers.
+#[tracing::instrument(skip_all)]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email
+        )
+        SELECT $1, email
+        FROM subscriptions
+        WHERE status = 'confirmed'
+        "#,
+        newsletter_issue_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_deliveries (
+            newsletter_issue_id,
+            subscriber_email,
+            status,
+            queued_at,
+            updated_at
+        )
+        SELECT newsletter_issue_id, subscriber_email, $2, now(), now()
+        FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+        DeliveryStatus::Queued.as_str()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    notify_workers(transaction).await?;
+
+    Ok(())
+}
+
+/// Lock up to `batch_size` due tasks, skipping those of paused or cancelled issues.
 #[tracing::instrument(skip_all)]
-async fn dequeue_task(
+async fn dequeue_tasks(
     pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+    batch_size: i64,
+) -> Result<(PgTransaction, Vec<Task>), anyhow::Error> {
     let mut transaction = pool.begin().await?;
-    let r = sqlx::query!(
+    let tasks = sqlx::query_as!(
+        Task,
         r#"
-        SELECT newsletter_issue_id, subscriber_email
-        FROM issue_delivery_queue
-        FOR UPDATE
+        SELECT
+            q.newsletter_issue_id AS issue_id,
+            q.subscriber_email AS email,
+            q.n_retries
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i ON i.newsletter_issue_id = q.newsletter_issue_id
+        WHERE
+            q.execute_after <= now() AND
+            i.status = $2
+        FOR UPDATE OF q
         SKIP LOCKED
-        LIMIT 1
-        "#
+        LIMIT $1
+        "#,
+        batch_size,
+        IssueStatus::Published.as_str()
     )
-    .fetch_optional(transaction.as_mut())
+    .fetch_all(transaction.as_mut())
     .await?;
-    if let Some(r) = r {
-        Ok(Some((
-            transaction,
-            r.newsletter_issue_id,
-            r.subscriber_email,
-        )))
-    } else {
-        Ok(None)
-    }
+
+    Ok((transaction, tasks))
 }
 
 #[tracing::instrument(skip_all)]
-async fn delete_task(
-    mut transaction: PgTransaction,
-    issue_id: Uuid,
-    email: &str,
-) -> Result<(), anyhow::Error> {
+async fn delete_task(transaction: &mut PgTransaction, task: &Task) -> Result<(), anyhow::Error> {
     sqlx::query!(
         r#"
         DELETE FROM issue_delivery_queue
@@ -133,33 +488,213 @@ async fn delete_task(
             newsletter_issue_id = $1 AND
             subscriber_email = $2
         "#,
-        issue_id,
-        email
+        task.issue_id,
+        task.email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn retry_task_later(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    let delay_milliseconds = i64::try_from(delay.as_millis())?;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = now() + $3 * interval '1 millisecond'
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        task.issue_id,
+        task.email,
+        delay_milliseconds as f64
     )
     .execute(transaction.as_mut())
     .await?;
-    transaction.commit().await?;
 
     Ok(())
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
-    loop {
-        match try_execute_task(&pool, &email_client).await {
-            Ok(ExecutionOutcome::EmptyQueue) => {
-                tokio::time::sleep(Duration::from_secs(10)).await;
+#[tracing::instrument(skip_all)]
+async fn dead_letter_task(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    error: &anyhow::Error,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_dead_letters (
+            newsletter_issue_id,
+            subscriber_email,
+            n_retries,
+            last_error,
+            failed_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET
+            n_retries = EXCLUDED.n_retries,
+            last_error = EXCLUDED.last_error,
+            failed_at = EXCLUDED.failed_at
+        "#,
+        task.issue_id,
+        task.email,
+        task.n_retries,
+        error.to_string()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    delete_task(transaction, task).await
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_client: Arc<EmailClient>,
+    base_url: String,
+    hmac_secret: Secret<String>,
+    settings: WorkerSettings,
+    rate_limiter: Arc<RateLimiter>,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let mut listener = PgListener::connect_with(&pool).await?;
+    listener.listen(DELIVERY_QUEUE_CHANNEL).await?;
+    // Shutdown is only checked between tasks: a batch always runs to completion,
+    // committing or rolling back its transaction.
+    while !shutdown.is_cancelled() {
+        // Failures are already logged, and retried on the next iteration
+        let _ = publish_due_issues(&pool).await;
+        let deliveries = try_execute_task(
+            &pool,
+            &email_client,
+            &base_url,
+            &hmac_secret,
+            &settings,
+            &rate_limiter,
+        )
+        .await;
+        // Transactional emails are not held back by the fan-out of a large issue
+        let transactional_emails = try_send_outbox_emails(
+            &pool,
+            &email_client,
+            &base_url,
+            &hmac_secret,
+            &settings,
+            &rate_limiter,
+        )
+        .await;
+        match (deliveries, transactional_emails) {
+            (Ok(ExecutionOutcome::EmptyQueue), Ok(ExecutionOutcome::EmptyQueue)) => {
+                let timeout = idle_timeout(&pool, settings.poll_interval()).await;
+                tokio::select! {
+                    () = wait_for_new_tasks(&mut listener, timeout) => {}
+                    () = shutdown.cancelled() => {}
+                }
             }
-            Err(_) => {
-                tokio::time::sleep(Duration::from_secs(1)).await;
+            (Err(_), _) | (_, Err(_)) => {
+                tokio::select! {
+                    () = tokio::time::sleep(settings.error_backoff()) => {}
+                    () = shutdown.cancelled() => {}
+                }
             }
-            Ok(ExecutionOutcome::TaskCompleted) => {}
+            (Ok(_), Ok(_)) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// How long an idle worker can wait before it has to check the queue again:
+/// the poll interval, or less if a scheduled issue falls due sooner.
+async fn idle_timeout(pool: &PgPool, poll_interval: Duration) -> Duration {
+    match next_scheduled_send(pool).await {
+        Ok(Some(next)) => (next - Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO)
+            .min(poll_interval),
+        Ok(None) | Err(_) => poll_interval,
+    }
+}
+
+/// Block until a new delivery is enqueued or `poll_interval` elapses.
+///
+/// Polling is only a safety net: it picks up retries whose backoff has expired
+/// and any notification lost while the listener was reconnecting.
+async fn wait_for_new_tasks(listener: &mut PgListener, poll_interval: Duration) {
+    match tokio::time::timeout(poll_interval, listener.recv()).await {
+        Ok(Ok(_)) | Err(_) => {}
+        Ok(Err(e)) => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to wait for queue notifications. Falling back to polling."
+            );
+            tokio::time::sleep(poll_interval).await;
         }
     }
 }
 
-pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
-    let connection_pool = get_connection_pool(&configuration.database);
-    let email_client = configuration.email_client.client();
+/// Run `worker.concurrency` delivery loops, sharing a single rate limiter, and the cleanup of
+/// abandoned subscriptions until `shutdown` is cancelled.
+///
+/// Returns as soon as any of them fails, aborting the others. Once shutdown is requested the
+/// loops get the drain timeout to finish their current batch; past it they are aborted and
+/// their transactions rolled back, leaving the tasks in the queue.
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let settings = configuration.worker;
+    anyhow::ensure!(
+        settings.concurrency > 0,
+        "The worker needs a concurrency of at least 1"
+    );
+    let connection_pool =
+        get_connection_pool_with_size(&configuration.database, settings.connection_pool_size());
+    let email_client = Arc::new(configuration.email_client.client());
+    let rate_limiter = Arc::new(RateLimiter::new(settings.messages_per_second));
+    let drain_timeout = configuration.application.drain_timeout();
 
-    worker_loop(connection_pool, email_client).await
+    let mut workers = JoinSet::new();
+    for _ in 0..settings.concurrency {
+        workers.spawn(worker_loop(
+            connection_pool.clone(),
+            email_client.clone(),
+            configuration.application.base_url.clone(),
+            configuration.application.hmac_secret.clone(),
+            settings.clone(),
+            rate_limiter.clone(),
+            shutdown.clone(),
+        ));
+    }
+    workers.spawn(cleanup_loop(
+        connection_pool.clone(),
+        configuration.subscriptions.clone(),
+        shutdown.clone(),
+    ));
+    let join_all = async {
+        while let Some(outcome) = workers.join_next().await {
+            outcome??;
+        }
+        Ok(())
+    };
+    let drain_deadline = async {
+        shutdown.cancelled().await;
+        tokio::time::sleep(drain_timeout).await;
+    };
+
+    tokio::select! {
+        outcome = join_all => outcome,
+        () = drain_deadline => {
+            anyhow::bail!("Delivery loops failed to drain within {drain_timeout:?}")
+        }
+    }
 }
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{domain::IssueStatus, issue_delivery_worker::enqueue_delivery_tasks};
+
+/// Publish every scheduled issue whose send time has arrived, fanning out its delivery tasks.
+///
+/// Safe to run from several workers at once: each issue is locked while it is published.
+#[tracing::instrument(skip_all, err)]
+pub async fn publish_due_issues(pool: &PgPool) -> Result<Vec<Uuid>, anyhow::Error> {
+    let mut published = Vec::new();
+    while let Some(issue_id) = try_publish_due_issue(pool).await? {
+        tracing::info!(newsletter_issue_id = %issue_id, "Published a scheduled newsletter issue");
+        published.push(issue_id);
+    }
+
+    Ok(published)
+}
+
+async fn try_publish_due_issue(pool: &PgPool) -> Result<Option<Uuid>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let issue_id = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id
+        FROM newsletter_issues
+        WHERE
+            status = $1 AND
+            scheduled_for <= now()
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+        IssueStatus::Scheduled.as_str()
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?
+    .map(|r| r.newsletter_issue_id);
+    let Some(issue_id) = issue_id else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            status = $2,
+            published_at = now()
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id,
+        IssueStatus::Published.as_str()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    enqueue_delivery_tasks(&mut transaction, issue_id).await?;
+    transaction.commit().await?;
+
+    Ok(Some(issue_id))
+}
+
+/// When the earliest pending scheduled issue is due, if there is one.
+#[tracing::instrument(skip_all)]
+pub async fn next_scheduled_send(pool: &PgPool) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+    let next = sqlx::query!(
+        r#"
+        SELECT min(scheduled_for) AS next
+        FROM newsletter_issues
+        WHERE status = $1
+        "#,
+        IssueStatus::Scheduled.as_str()
+    )
+    .fetch_one(pool)
+    .await?
+    .next;
+
+    Ok(next)
+}
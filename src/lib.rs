@@ -2,10 +2,15 @@ pub mod authentication;
 pub mod configuration;
 pub mod domain;
 pub mod email_client;
+pub mod email_outbox;
 mod idempotency;
 pub mod issue_delivery_worker;
+pub mod issue_scheduler;
+pub mod rate_limiter;
 pub mod routes;
 pub mod session_state;
 pub mod startup;
+pub mod subscription_cleanup;
 pub mod telemetry;
 pub mod utils;
+pub mod welcome_email;
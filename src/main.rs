@@ -1,34 +1,152 @@
 #![warn(clippy::pedantic)]
 
+use anyhow::Context;
 use std::fmt::{Debug, Display};
-use tokio::task::JoinError;
+use tokio::task::{JoinError, JoinHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
 use zero_to_prod::issue_delivery_worker::run_worker_until_stopped;
+use zero_to_prod::startup::get_connection_pool;
 use zero_to_prod::telemetry;
 use zero_to_prod::{configuration::get_configuration, startup::Application};
 
+const USAGE: &str = "\
+Usage: zero-to-prod [COMMAND]
+
+Commands:
+  serve    Run the API server
+  worker   Run the issue delivery worker
+  migrate  Apply pending database migrations and exit
+  all      Run both the API server and the delivery worker (default)";
+
+/// What this process should run, so that web nodes and workers can be scaled independently.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Command {
+    Serve,
+    Worker,
+    Migrate,
+    All,
+}
+
+impl Command {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, anyhow::Error> {
+        let command = match args.next().as_deref() {
+            None | Some("all") => Self::All,
+            Some("serve") => Self::Serve,
+            Some("worker") => Self::Worker,
+            Some("migrate") => Self::Migrate,
+            Some(other) => anyhow::bail!("Unknown command `{other}`.\n\n{USAGE}"),
+        };
+        if let Some(extra) = args.next() {
+            anyhow::bail!("Unexpected argument `{extra}`.\n\n{USAGE}");
+        }
+
+        Ok(command)
+    }
+
+    fn runs_api(self) -> bool {
+        matches!(self, Self::Serve | Self::All)
+    }
+
+    fn runs_worker(self) -> bool {
+        matches!(self, Self::Worker | Self::All)
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let command = Command::from_args(std::env::args().skip(1))?;
+
     let subscriber =
         telemetry::get_subscriber("zero-to-prod".into(), "info".into(), std::io::stdout);
     telemetry::init_subscriber(subscriber);
 
     let configuration = get_configuration().expect("Failed to read configuration.");
-    let application = Application::build(configuration.clone()).await?;
-    let application_task = tokio::spawn(application.run_until_stopped());
-    let worker_task = tokio::spawn(run_worker_until_stopped(configuration));
+    if command == Command::Migrate {
+        let connection_pool = get_connection_pool(&configuration.database);
+        sqlx::migrate!()
+            .run(&connection_pool)
+            .await
+            .context("Failed to migrate the database")?;
+        tracing::info!("Database migrations applied");
+        return Ok(());
+    }
 
-    tokio::select! {
-        o = application_task => report_exit("API", o),
-        o = worker_task => report_exit("Background worker", o),
-    };
+    let shutdown = CancellationToken::new();
+    let mut supervisors = JoinSet::new();
+    if command.runs_api() {
+        let application = Application::build(configuration.clone()).await?;
+        let application_task = tokio::spawn(application.run_until_stopped(shutdown.clone()));
+        supervisors.spawn(supervise("API", application_task, shutdown.clone()));
+    }
+    if command.runs_worker() {
+        let worker_task = tokio::spawn(run_worker_until_stopped(configuration, shutdown.clone()));
+        supervisors.spawn(supervise(
+            "Background worker",
+            worker_task,
+            shutdown.clone(),
+        ));
+    }
+    tokio::spawn(cancel_on_signal(shutdown));
+
+    let mut clean_exit = true;
+    while let Some(outcome) = supervisors.join_next().await {
+        clean_exit &= outcome?;
+    }
+    if !clean_exit {
+        anyhow::bail!("Failed to shut down cleanly");
+    }
 
     Ok(())
 }
 
-fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+/// Request a shutdown on SIGTERM (what orchestrators send) or Ctrl-C.
+async fn cancel_on_signal(shutdown: CancellationToken) {
+    let ctrl_c = tokio::signal::ctrl_c();
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install the SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        () = terminate => {}
+    }
+    tracing::info!("Shutdown requested, draining in-flight work");
+    shutdown.cancel();
+}
+
+/// Wait for `task` to exit, then bring the rest of the process down with it.
+///
+/// Returns whether it exited cleanly, i.e. successfully and because shutdown was requested.
+async fn supervise<E: Debug + Display>(
+    task_name: &'static str,
+    task: JoinHandle<Result<(), E>>,
+    shutdown: CancellationToken,
+) -> bool {
+    let outcome = task.await;
+    let requested = shutdown.is_cancelled();
+    shutdown.cancel();
+    report_exit(task_name, outcome, requested)
+}
+
+fn report_exit(
+    task_name: &str,
+    outcome: Result<Result<(), impl Debug + Display>, JoinError>,
+    shutdown_requested: bool,
+) -> bool {
     match outcome {
+        Ok(Ok(())) if shutdown_requested => {
+            tracing::info!("{task_name} has shut down");
+            true
+        }
         Ok(Ok(())) => {
             tracing::error!("{task_name} has exited");
+            false
         }
         Ok(Err(e)) => {
             tracing::error!(
@@ -36,6 +154,7 @@ fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>
                 error_message = %e,
                 "{task_name} has failed"
             );
+            false
         }
         Err(e) => {
             tracing::error!(
@@ -43,6 +162,35 @@ fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>
                 error.message = %e,
                 "{task_name} task faild to complete"
             );
+            false
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+
+    fn parse(args: &[&str]) -> Result<Command, anyhow::Error> {
+        Command::from_args(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn no_subcommand_runs_everything() {
+        assert_eq!(parse(&[]).unwrap(), Command::All);
+    }
+
+    #[test]
+    fn subcommands_are_parsed() {
+        assert_eq!(parse(&["serve"]).unwrap(), Command::Serve);
+        assert_eq!(parse(&["worker"]).unwrap(), Command::Worker);
+        assert_eq!(parse(&["migrate"]).unwrap(), Command::Migrate);
+        assert_eq!(parse(&["all"]).unwrap(), Command::All);
+    }
+
+    #[test]
+    fn unknown_or_extra_arguments_are_rejected() {
+        assert!(parse(&["deliver"]).is_err());
+        assert!(parse(&["serve", "worker"]).is_err());
+    }
+}
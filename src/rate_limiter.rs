@@ -0,0 +1,107 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// A token bucket capping how many messages per second are handed to the email provider.
+///
+/// The bucket holds up to one second worth of tokens. Callers that take more tokens than are
+/// available go into debt and wait for it to be repaid, so a batch larger than the per-second
+/// budget still goes through, just later.
+pub struct RateLimiter {
+    messages_per_second: f64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(messages_per_second: u32) -> Self {
+        let messages_per_second = f64::from(messages_per_second.max(1));
+        Self {
+            messages_per_second,
+            state: Mutex::new(BucketState {
+                tokens: messages_per_second,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until `n_messages` can be sent without exceeding the rate limit.
+    pub async fn acquire(&self, n_messages: usize) {
+        let wait = self.reserve(n_messages, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Take `n_messages` tokens out of the bucket, returning how long the caller has to wait
+    /// before sending them.
+    #[allow(clippy::cast_precision_loss)]
+    fn reserve(&self, n_messages: usize, now: Instant) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = now.saturating_duration_since(state.last_refill);
+        state.tokens = (state.tokens + elapsed.as_secs_f64() * self.messages_per_second)
+            .min(self.messages_per_second);
+        state.last_refill = now;
+        state.tokens -= n_messages as f64;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.messages_per_second)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use claims::assert_gt;
+    use tokio::time::Instant;
+
+    use super::RateLimiter;
+
+    #[test]
+    fn a_full_bucket_lets_one_second_worth_of_messages_through() {
+        let limiter = RateLimiter::new(10);
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(10, now), Duration::ZERO);
+    }
+
+    #[test]
+    fn exceeding_the_budget_waits_for_the_debt_to_be_repaid() {
+        let limiter = RateLimiter::new(10);
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve(15, now), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn the_bucket_refills_over_time() {
+        let limiter = RateLimiter::new(10);
+        let now = Instant::now();
+        limiter.reserve(10, now);
+
+        assert_gt!(limiter.reserve(1, now), Duration::ZERO);
+        assert_eq!(
+            limiter.reserve(4, now + Duration::from_millis(500)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn the_bucket_never_holds_more_than_one_second_worth_of_tokens() {
+        let limiter = RateLimiter::new(10);
+        let now = Instant::now();
+
+        assert_gt!(
+            limiter.reserve(11, now + Duration::from_secs(60)),
+            Duration::ZERO
+        );
+    }
+}
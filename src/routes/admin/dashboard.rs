@@ -28,7 +28,13 @@ pub async fn admin_dashboard(
     <p>Available actions:</p>
     <ol>
         <li><a href="/admin/newsletters">Send a newsletter issue</a></li>
+        <li><a href="/admin/drafts">Draft newsletter issues</a></li>
+        <li><a href="/admin/issues">Published newsletter issues</a></li>
+        <li><a href="/admin/subscribers">Manage subscribers</a></li>
+        <li><a href="/admin/welcome_email">Edit the welcome email</a></li>
+        <li><a href="/admin/dead_letters">Review failed deliveries</a></li>
         <li><a href="/admin/password">Change password</a></li>
+        <li><a href="/admin/email">Change email address</a></li>
         <li>
             <form name="logoutForm" action="/admin/logout" method="post">
                 <input type="submit" value="Logout">
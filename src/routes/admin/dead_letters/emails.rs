@@ -0,0 +1,98 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    issue_delivery_worker::notify_workers,
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "Re-enqueue a dead-lettered transactional email",
+    skip(form, pool)
+)]
+pub async fn requeue_email_dead_letter(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    let requeued = requeue(&mut transaction, form.id)
+        .await
+        .context("Failed to move a dead-lettered email back to the outbox")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to re-enqueue an email.")
+        .map_err(e500)?;
+    if requeued {
+        FlashMessage::info("The email has been re-enqueued.").send();
+    } else {
+        FlashMessage::error("The email is no longer in the dead-letter table.").send();
+    }
+
+    Ok(see_other("/admin/dead_letters"))
+}
+
+/// Move an email from the dead-letter table back to the outbox with a fresh retry budget.
+///
+/// Welcome emails are rendered again when sent, so they are still skipped for subscribers
+/// who are no longer confirmed.
+#[tracing::instrument(skip(transaction))]
+async fn requeue(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let n_requeued_rows = sqlx::query!(
+        r#"
+        WITH dead_letter AS (
+            DELETE FROM email_outbox_dead_letters
+            WHERE id = $1
+            RETURNING
+                id,
+                recipient,
+                subject,
+                html_content,
+                text_content,
+                header_names,
+                header_values,
+                created_at,
+                welcome_subscriber_id
+        )
+        INSERT INTO email_outbox (
+            id,
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            header_names,
+            header_values,
+            created_at,
+            welcome_subscriber_id
+        )
+        SELECT *
+        FROM dead_letter
+        "#,
+        id
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    if n_requeued_rows == 0 {
+        return Ok(false);
+    }
+    notify_workers(transaction).await?;
+
+    Ok(true)
+}
@@ -0,0 +1,171 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::utils::e500;
+
+struct DeadLetter {
+    newsletter_issue_id: Uuid,
+    title: String,
+    subscriber_email: String,
+    n_retries: i32,
+    last_error: String,
+    failed_at: DateTime<Utc>,
+}
+
+/// A transactional email, e.g. a confirmation link, that exhausted its retries.
+struct EmailDeadLetter {
+    id: Uuid,
+    recipient: String,
+    subject: String,
+    n_retries: i32,
+    last_error: String,
+    failed_at: DateTime<Utc>,
+}
+
+pub async fn dead_letters(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let mut rows_html = String::new();
+    for d in get_dead_letters(&pool).await.map_err(e500)? {
+        writeln!(
+            rows_html,
+            r#"        <tr>
+            <td>{title}</td>
+            <td>{email}</td>
+            <td>{n_retries}</td>
+            <td>{last_error}</td>
+            <td>{failed_at}</td>
+            <td>
+                <form action="/admin/dead_letters" method="post">
+                    <input hidden type="text" name="newsletter_issue_id" value="{issue_id}">
+                    <input hidden type="text" name="subscriber_email" value="{email}">
+                    <button type="submit">Re-enqueue</button>
+                </form>
+            </td>
+        </tr>"#,
+            title = encode_minimal(&d.title),
+            email = encode_minimal(&d.subscriber_email),
+            n_retries = d.n_retries,
+            last_error = encode_minimal(&d.last_error),
+            failed_at = d.failed_at.to_rfc3339(),
+            issue_id = d.newsletter_issue_id,
+        )
+        .unwrap();
+    }
+    let mut email_rows_html = String::new();
+    for d in get_email_dead_letters(&pool).await.map_err(e500)? {
+        writeln!(
+            email_rows_html,
+            r#"        <tr>
+            <td>{subject}</td>
+            <td>{recipient}</td>
+            <td>{n_retries}</td>
+            <td>{last_error}</td>
+            <td>{failed_at}</td>
+            <td>
+                <form action="/admin/dead_letters/emails" method="post">
+                    <input hidden type="text" name="id" value="{id}">
+                    <button type="submit">Re-enqueue</button>
+                </form>
+            </td>
+        </tr>"#,
+            subject = encode_minimal(&d.subject),
+            recipient = encode_minimal(&d.recipient),
+            n_retries = d.n_retries,
+            last_error = encode_minimal(&d.last_error),
+            failed_at = d.failed_at.to_rfc3339(),
+            id = d.id,
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Failed deliveries</title>
+</head>
+<body>
+    {msg_html}
+    <h2>Newsletter deliveries</h2>
+    <table>
+        <tr>
+            <th>Issue</th>
+            <th>Subscriber</th>
+            <th>Retries</th>
+            <th>Last error</th>
+            <th>Failed at</th>
+            <th></th>
+        </tr>
+{rows_html}    </table>
+    <h2>Transactional emails</h2>
+    <table>
+        <tr>
+            <th>Subject</th>
+            <th>Recipient</th>
+            <th>Retries</th>
+            <th>Last error</th>
+            <th>Failed at</th>
+            <th></th>
+        </tr>
+{email_rows_html}    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#
+        )))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_dead_letters(pool: &PgPool) -> Result<Vec<DeadLetter>, anyhow::Error> {
+    let dead_letters = sqlx::query_as!(
+        DeadLetter,
+        r#"
+        SELECT
+            d.newsletter_issue_id,
+            i.title,
+            d.subscriber_email,
+            d.n_retries,
+            d.last_error,
+            d.failed_at
+        FROM issue_delivery_dead_letters d
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        ORDER BY d.failed_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve dead-lettered deliveries.")?;
+
+    Ok(dead_letters)
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_email_dead_letters(pool: &PgPool) -> Result<Vec<EmailDeadLetter>, anyhow::Error> {
+    let dead_letters = sqlx::query_as!(
+        EmailDeadLetter,
+        r#"
+        SELECT id, recipient, subject, n_retries, last_error, failed_at
+        FROM email_outbox_dead_letters
+        ORDER BY failed_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve dead-lettered transactional emails.")?;
+
+    Ok(dead_letters)
+}
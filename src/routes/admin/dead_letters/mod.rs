@@ -0,0 +1,6 @@
+mod emails;
+pub use emails::requeue_email_dead_letter;
+mod get;
+pub use get::dead_letters;
+mod post;
+pub use post::requeue_dead_letter;
@@ -0,0 +1,165 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    domain::{DeliveryStatus, IssueStatus, SubscriptionStatus},
+    issue_delivery_worker::notify_workers,
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+}
+
+#[tracing::instrument(name = "Re-enqueue a dead-lettered delivery", skip(form, pool))]
+pub async fn requeue_dead_letter(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    let outcome = requeue(
+        &mut transaction,
+        form.newsletter_issue_id,
+        &form.subscriber_email,
+    )
+    .await
+    .context("Failed to move a dead-lettered delivery back to the queue")
+    .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to re-enqueue a delivery.")
+        .map_err(e500)?;
+    match outcome {
+        RequeueOutcome::Requeued => {
+            FlashMessage::info("The delivery has been re-enqueued.").send();
+        }
+        RequeueOutcome::Skipped(DeliveryStatus::Cancelled) => {
+            FlashMessage::error("The delivery was not re-enqueued: the issue has been cancelled.")
+                .send();
+        }
+        RequeueOutcome::Skipped(_) => {
+            FlashMessage::error(
+                "The delivery was not re-enqueued: the recipient is no longer subscribed.",
+            )
+            .send();
+        }
+        RequeueOutcome::Missing => {
+            FlashMessage::error("The delivery is no longer in the dead-letter table.").send();
+        }
+    }
+
+    Ok(see_other("/admin/dead_letters"))
+}
+
+enum RequeueOutcome {
+    Requeued,
+    /// The delivery no longer qualifies and was recorded with this status instead.
+    Skipped(DeliveryStatus),
+    Missing,
+}
+
+/// Move a delivery from the dead-letter table back to the queue with a fresh retry budget.
+///
+/// Deliveries to recipients who are no longer confirmed, or of issues that have been cancelled,
+/// are dropped from the dead-letter table and recorded as skipped instead.
+#[tracing::instrument(skip(transaction))]
+async fn requeue(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    subscriber_email: &str,
+) -> Result<RequeueOutcome, sqlx::Error> {
+    let n_deleted_rows = sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_dead_letters
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        newsletter_issue_id,
+        subscriber_email
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    if n_deleted_rows == 0 {
+        return Ok(RequeueOutcome::Missing);
+    }
+    let n_requeued_rows = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email
+        )
+        SELECT i.newsletter_issue_id, s.email
+        FROM newsletter_issues i
+        JOIN subscriptions s ON s.email = $2
+        WHERE
+            i.newsletter_issue_id = $1 AND
+            i.status IN ($3, $4) AND
+            s.status = $5
+        ON CONFLICT DO NOTHING
+        "#,
+        newsletter_issue_id,
+        subscriber_email,
+        IssueStatus::Published.as_str(),
+        IssueStatus::Paused.as_str(),
+        SubscriptionStatus::Confirmed.as_str()
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    let outcome = if n_requeued_rows > 0 {
+        RequeueOutcome::Requeued
+    } else {
+        let issue_status = sqlx::query_scalar!(
+            r#"
+            SELECT status
+            FROM newsletter_issues
+            WHERE newsletter_issue_id = $1
+            "#,
+            newsletter_issue_id
+        )
+        .fetch_optional(transaction.as_mut())
+        .await?;
+        if issue_status.as_deref() == Some(IssueStatus::Cancelled.as_str()) {
+            RequeueOutcome::Skipped(DeliveryStatus::Cancelled)
+        } else {
+            RequeueOutcome::Skipped(DeliveryStatus::SkippedUnsubscribed)
+        }
+    };
+    let delivery_status = match &outcome {
+        RequeueOutcome::Skipped(status) => *status,
+        _ => DeliveryStatus::Queued,
+    };
+    sqlx::query!(
+        r#"
+        UPDATE issue_deliveries
+        SET
+            status = $3,
+            updated_at = now()
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        newsletter_issue_id,
+        subscriber_email,
+        delivery_status.as_str()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    if let RequeueOutcome::Requeued = outcome {
+        notify_workers(transaction).await?;
+    }
+
+    Ok(outcome)
+}
@@ -0,0 +1,53 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_attribute;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use super::get_account_email;
+use crate::{authentication::UserId, utils::e500};
+
+pub async fn account_email_form(
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let email = get_account_email(&pool, user_id.into_inner())
+        .await
+        .map_err(e500)?
+        .unwrap_or_default();
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Email address</title>
+</head>
+<body>
+    {msg_html}
+    <p>Test emails for draft newsletter issues are sent to this address.</p>
+    <form action="/admin/email" method="post">
+        <label>Email
+            <input
+                type="email"
+                placeholder="Enter your email address"
+                name="email"
+                value="{email}"
+            >
+        </label>
+        <br>
+        <button type="submit">Save</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+            email = encode_attribute(&email),
+        )))
+}
@@ -0,0 +1,29 @@
+mod get;
+pub use get::account_email_form;
+mod post;
+pub use post::change_account_email;
+
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::authentication::UserId;
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_account_email(
+    pool: &PgPool,
+    user_id: UserId,
+) -> Result<Option<String>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT email
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id.0
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to retrieve the email address of the current user.")?;
+
+    Ok(row.email)
+}
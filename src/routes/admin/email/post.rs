@@ -0,0 +1,43 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::{
+    authentication::UserId,
+    domain::SubscriberEmail,
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+}
+
+#[tracing::instrument(name = "Change the email address of an admin", skip(form, pool))]
+pub async fn change_account_email(
+    form: web::Form<FormData>,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Ok(email) = SubscriberEmail::parse(form.0.email) else {
+        FlashMessage::error("The email address is invalid.").send();
+        return Ok(see_other("/admin/email"));
+    };
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET email = $2
+        WHERE user_id = $1
+        "#,
+        user_id.into_inner().0,
+        email.as_ref()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to update the email address of the current user.")
+    .map_err(e500)?;
+    FlashMessage::info("Your email address has been changed.").send();
+
+    Ok(see_other("/admin/email"))
+}
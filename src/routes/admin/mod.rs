@@ -1,8 +1,23 @@
 mod dashboard;
 pub use dashboard::admin_dashboard;
+mod dead_letters;
+pub use dead_letters::{dead_letters, requeue_dead_letter, requeue_email_dead_letter};
+mod email;
+pub use email::{account_email_form, change_account_email};
+mod pagination;
 mod password;
 pub use password::{change_password, change_password_form};
 mod logout;
 pub use logout::logout;
 mod newsletters;
-pub use newsletters::{publish_newsletter, publish_newsletter_form};
+pub use newsletters::{
+    cancel_newsletter_issue, create_draft, draft_form, drafts, newsletter_issue_detail,
+    newsletter_issues, pause_newsletter_issue, preview_draft, publish_draft, publish_newsletter,
+    publish_newsletter_form, resume_newsletter_issue, send_test_email, update_draft,
+};
+mod subscribers;
+pub use subscribers::{
+    cancel_subscription, confirm_subscriber, delete_subscriber, subscriber_detail, subscribers,
+};
+mod welcome_email;
+pub use welcome_email::{update_welcome_email, welcome_email_form};
@@ -0,0 +1,114 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    domain::{DeliveryStatus, IssueStatus},
+    utils::{e500, see_other},
+};
+
+#[tracing::instrument(name = "Cancel a newsletter issue", skip(pool))]
+pub async fn cancel_newsletter_issue(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    let cancelled = cancel(&mut transaction, newsletter_issue_id)
+        .await
+        .context("Failed to cancel a newsletter issue")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to cancel a newsletter issue.")
+        .map_err(e500)?;
+    if cancelled {
+        FlashMessage::info("The newsletter issue has been cancelled.").send();
+    } else {
+        FlashMessage::error("Only scheduled or undelivered issues can be cancelled.").send();
+    }
+
+    Ok(see_other(&format!(
+        "/admin/newsletters/{newsletter_issue_id}"
+    )))
+}
+
+/// Stop an issue for good: drop its remaining delivery tasks and record those recipients as
+/// cancelled in the delivery log.
+///
+/// Published or paused issues can only be cancelled while deliveries are left in the queue:
+/// an issue whose delivery has finished stays published.
+///
+/// Tasks a worker is sending right now are locked: we wait for that batch to finish.
+#[tracing::instrument(skip(transaction))]
+async fn cancel(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $2
+        WHERE
+            newsletter_issue_id = $1 AND
+            (
+                status = $3 OR
+                (
+                    status = ANY($4) AND
+                    EXISTS (
+                        SELECT 1
+                        FROM issue_delivery_queue
+                        WHERE newsletter_issue_id = $1
+                    )
+                )
+            )
+        "#,
+        newsletter_issue_id,
+        IssueStatus::Cancelled.as_str(),
+        IssueStatus::Scheduled.as_str(),
+        &[
+            IssueStatus::Published.as_str().to_owned(),
+            IssueStatus::Paused.as_str().to_owned(),
+        ]
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    if n_updated_rows == 0 {
+        return Ok(false);
+    }
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE issue_deliveries
+        SET
+            status = $3,
+            updated_at = now()
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        "#,
+        newsletter_issue_id,
+        DeliveryStatus::Queued.as_str(),
+        DeliveryStatus::Cancelled.as_str()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(true)
+}
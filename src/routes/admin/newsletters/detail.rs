@@ -0,0 +1,201 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::{
+    domain::{DeliveryStatus, IssueStatus},
+    utils::{e404, e500},
+};
+
+struct IssueSummary {
+    title: String,
+    status: String,
+    published_at: Option<DateTime<Utc>>,
+    scheduled_for: Option<DateTime<Utc>>,
+    hidden_from_archive: bool,
+}
+
+#[derive(Default)]
+struct DeliveryCounts {
+    queued: i64,
+    sent: i64,
+    failed: i64,
+    skipped: i64,
+    cancelled: i64,
+}
+
+impl DeliveryCounts {
+    fn total(&self) -> i64 {
+        self.queued + self.sent + self.failed + self.skipped + self.cancelled
+    }
+}
+
+#[tracing::instrument(
+    name = "Show newsletter issue delivery progress",
+    skip(pool, flash_messages)
+)]
+pub async fn newsletter_issue_detail(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let issue = get_issue_summary(&pool, newsletter_issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("There is no newsletter issue with the provided id."))?;
+    let counts = get_delivery_counts(&pool, newsletter_issue_id)
+        .await
+        .map_err(e500)?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let title = encode_minimal(&issue.title);
+    let state = match issue.status.as_str() {
+        s if s == IssueStatus::Scheduled.as_str() => format!(
+            r#"<p>Scheduled for {scheduled_for}</p>
+    <form action="/admin/newsletters/{newsletter_issue_id}/cancel" method="post">
+        <button type="submit">Cancel</button>
+    </form>"#,
+            scheduled_for = issue
+                .scheduled_for
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+        ),
+        s if s == IssueStatus::Draft.as_str() => {
+            format!(r#"<p>Draft. <a href="/admin/drafts/{newsletter_issue_id}">Edit</a></p>"#)
+        }
+        s if s == IssueStatus::Cancelled.as_str() => "<p>Cancelled.</p>".to_string(),
+        s if s == IssueStatus::Paused.as_str() => format!(
+            r#"<p>Delivery paused.</p>
+    <form action="/admin/newsletters/{newsletter_issue_id}/resume" method="post">
+        <button type="submit">Resume</button>
+    </form>
+    <form action="/admin/newsletters/{newsletter_issue_id}/cancel" method="post">
+        <button type="submit">Cancel remaining deliveries</button>
+    </form>"#
+        ),
+        _ if counts.queued == 0 => format!(
+            "<p>Published at {published_at}</p>\n    <p>Delivery finished.</p>",
+            published_at = issue
+                .published_at
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+        ),
+        _ => format!(
+            r#"<p>Published at {published_at}</p>
+    <p>Delivery in progress...</p>
+    <form action="/admin/newsletters/{newsletter_issue_id}/pause" method="post">
+        <button type="submit">Pause</button>
+    </form>
+    <form action="/admin/newsletters/{newsletter_issue_id}/cancel" method="post">
+        <button type="submit">Cancel remaining deliveries</button>
+    </form>"#,
+            published_at = issue
+                .published_at
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+        ),
+    };
+    let archive = if issue.hidden_from_archive {
+        "Hidden from the public archive.".to_string()
+    } else if issue.status == IssueStatus::Published.as_str()
+        || issue.status == IssueStatus::Paused.as_str()
+    {
+        format!(r#"<a href="/issues/{newsletter_issue_id}">View online</a>"#)
+    } else {
+        "Listed in the public archive once published.".to_string()
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+<body>
+    {msg_html}
+    <h1>{title}</h1>
+    {state}
+    <p>{archive}</p>
+    <table>
+        <tr><th>Total</th><td>{total}</td></tr>
+        <tr><th>Pending</th><td>{queued}</td></tr>
+        <tr><th>Sent</th><td>{sent}</td></tr>
+        <tr><th>Failed</th><td>{failed}</td></tr>
+        <tr><th>Skipped</th><td>{skipped}</td></tr>
+        <tr><th>Cancelled</th><td>{cancelled}</td></tr>
+    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+            total = counts.total(),
+            queued = counts.queued,
+            sent = counts.sent,
+            failed = counts.failed,
+            skipped = counts.skipped,
+            cancelled = counts.cancelled,
+        )))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_issue_summary(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<IssueSummary>, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT title, status, published_at, scheduled_for, hidden_from_archive
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve newsletter issue.")?;
+
+    Ok(issue)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_delivery_counts(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<DeliveryCounts, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT status, count(*) AS "count!"
+        FROM issue_deliveries
+        WHERE newsletter_issue_id = $1
+        GROUP BY status
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to count deliveries for a newsletter issue.")?;
+
+    let mut counts = DeliveryCounts::default();
+    for row in rows {
+        match row.status.as_str() {
+            s if s == DeliveryStatus::Queued.as_str() => counts.queued += row.count,
+            s if s == DeliveryStatus::Sent.as_str() => counts.sent += row.count,
+            s if s == DeliveryStatus::Failed.as_str() => counts.failed += row.count,
+            s if s == DeliveryStatus::Cancelled.as_str() => counts.cancelled += row.count,
+            _ => counts.skipped += row.count,
+        }
+    }
+
+    Ok(counts)
+}
@@ -0,0 +1,181 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::get_draft;
+use crate::{
+    domain::IssueStatus,
+    utils::{e404, e500},
+};
+
+pub async fn drafts(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let mut drafts_html = String::new();
+    for draft in get_drafts(&pool).await.map_err(e500)? {
+        writeln!(
+            drafts_html,
+            r#"<li><a href="/admin/drafts/{}">{}</a></li>"#,
+            draft.newsletter_issue_id,
+            encode_minimal(&draft.title),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Drafts</title>
+</head>
+<body>
+    {msg_html}
+    <ul>
+        {drafts_html}
+    </ul>
+    <h2>New draft</h2>
+    <form action="/admin/drafts" method="post">
+        <label>Title
+            <input
+                type="text"
+                placeholder="Enter Title"
+                name="title"
+            >
+        </label>
+        <br>
+        <label>Markdown Content (generates both bodies below)
+            <textarea name="markdown_content"></textarea>
+        </label>
+        <br>
+        <label>HTML Content
+            <textarea name="html_content"></textarea>
+        </label>
+        <br>
+        <label>Text Content
+            <textarea name="text_content"></textarea>
+        </label>
+        <br>
+        <button type="submit">Save draft</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(name = "Show a draft newsletter issue", skip(pool, flash_messages))]
+pub async fn draft_form(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let draft = get_draft(&pool, newsletter_issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("There is no draft with the provided id."))?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let idempotency_key = Uuid::new_v4();
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Edit draft</title>
+</head>
+<body>
+    {msg_html}
+    <form action="/admin/drafts/{newsletter_issue_id}" method="post">
+        <label>Title
+            <input
+                type="text"
+                placeholder="Enter Title"
+                name="title"
+                value="{title}"
+            >
+        </label>
+        <br>
+        <label>Markdown Content (generates both bodies below)
+            <textarea name="markdown_content">{markdown_content}</textarea>
+        </label>
+        <br>
+        <label>HTML Content
+            <textarea name="html_content">{html_content}</textarea>
+        </label>
+        <br>
+        <label>Text Content
+            <textarea name="text_content">{text_content}</textarea>
+        </label>
+        <br>
+        <button type="submit">Save draft</button>
+    </form>
+    <p><a href="/admin/drafts/{newsletter_issue_id}/preview">Preview</a></p>
+    <form action="/admin/drafts/{newsletter_issue_id}/test" method="post">
+        <button type="submit">Send a test email to myself</button>
+    </form>
+    <form action="/admin/drafts/{newsletter_issue_id}/publish" method="post">
+        <label>Send at (UTC, leave empty to send right away)
+            <input
+                type="datetime-local"
+                name="send_at"
+            >
+        </label>
+        <label>
+            <input type="checkbox" name="hide_from_archive">
+            Hide from the public archive
+        </label>
+        <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
+        <button type="submit">Publish</button>
+    </form>
+    <p><a href="/admin/drafts">&lt;- Back</a></p>
+</body>
+</html>"#,
+            title = encode_attribute(&draft.title),
+            markdown_content =
+                encode_minimal(draft.markdown_content.as_deref().unwrap_or_default()),
+            html_content = encode_minimal(&draft.html_content),
+            text_content = encode_minimal(&draft.text_content),
+        )))
+}
+
+struct DraftSummary {
+    newsletter_issue_id: Uuid,
+    title: String,
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_drafts(pool: &PgPool) -> Result<Vec<DraftSummary>, anyhow::Error> {
+    let drafts = sqlx::query_as!(
+        DraftSummary,
+        r#"
+        SELECT newsletter_issue_id, title
+        FROM newsletter_issues
+        WHERE status = $1
+        ORDER BY title
+        "#,
+        IssueStatus::Draft.as_str()
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve draft newsletter issues.")?;
+
+    Ok(drafts)
+}
@@ -0,0 +1,47 @@
+mod get;
+pub use get::{draft_form, drafts};
+mod post;
+pub use post::{create_draft, update_draft};
+mod preview;
+pub use preview::preview_draft;
+mod publish;
+pub use publish::publish_draft;
+mod send_test;
+pub use send_test::send_test_email;
+
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::IssueStatus;
+
+struct Draft {
+    title: String,
+    text_content: String,
+    html_content: String,
+    markdown_content: Option<String>,
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_draft(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<Draft>, anyhow::Error> {
+    let draft = sqlx::query_as!(
+        Draft,
+        r#"
+        SELECT title, text_content, html_content, markdown_content
+        FROM newsletter_issues
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        "#,
+        newsletter_issue_id,
+        IssueStatus::Draft.as_str()
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve a draft newsletter issue.")?;
+
+    Ok(draft)
+}
@@ -0,0 +1,137 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::super::post::{validate_templates, IssueContent};
+use crate::{
+    domain::IssueStatus,
+    utils::{e400, e500, see_other},
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    title: String,
+    html_content: Option<String>,
+    text_content: Option<String>,
+    /// When set, both bodies are generated from it.
+    markdown_content: Option<String>,
+}
+
+#[tracing::instrument(name = "Save a new draft newsletter issue", skip(form, pool))]
+pub async fn create_draft(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        title,
+        html_content,
+        text_content,
+        markdown_content,
+    } = form.0;
+    let content = IssueContent::new(html_content, text_content, markdown_content).map_err(e400)?;
+    // The archive visibility is only picked at publish time, which checks templates again
+    validate_templates(&content.html_content, &content.text_content, false).map_err(e400)?;
+    let newsletter_issue_id = insert_draft(&pool, &title, &content)
+        .await
+        .context("Failed to store draft details")
+        .map_err(e500)?;
+    FlashMessage::info("The draft has been saved.").send();
+
+    Ok(see_other(&format!("/admin/drafts/{newsletter_issue_id}")))
+}
+
+#[tracing::instrument(name = "Update a draft newsletter issue", skip(form, pool))]
+pub async fn update_draft(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let FormData {
+        title,
+        html_content,
+        text_content,
+        markdown_content,
+    } = form.0;
+    let content = IssueContent::new(html_content, text_content, markdown_content).map_err(e400)?;
+    // The archive visibility is only picked at publish time, which checks templates again
+    validate_templates(&content.html_content, &content.text_content, false).map_err(e400)?;
+    let updated = update(&pool, newsletter_issue_id, &title, &content)
+        .await
+        .context("Failed to update draft details")
+        .map_err(e500)?;
+    if !updated {
+        FlashMessage::error("Only drafts can be edited.").send();
+        return Ok(see_other("/admin/drafts"));
+    }
+    FlashMessage::info("The draft has been saved.").send();
+
+    Ok(see_other(&format!("/admin/drafts/{newsletter_issue_id}")))
+}
+
+#[tracing::instrument(skip_all)]
+async fn insert_draft(
+    pool: &PgPool,
+    title: &str,
+    content: &IssueContent,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            status
+        )
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        newsletter_issue_id,
+        title,
+        content.text_content,
+        content.html_content,
+        content.markdown_content,
+        IssueStatus::Draft.as_str()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(newsletter_issue_id)
+}
+
+#[tracing::instrument(skip(pool, title, content))]
+async fn update(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    title: &str,
+    content: &IssueContent,
+) -> Result<bool, sqlx::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $3,
+            text_content = $4,
+            html_content = $5,
+            markdown_content = $6
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        "#,
+        newsletter_issue_id,
+        IssueStatus::Draft.as_str(),
+        title,
+        content.text_content,
+        content.html_content,
+        content.markdown_content
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(n_updated_rows > 0)
+}
@@ -0,0 +1,46 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::get_draft;
+use crate::utils::{e404, e500};
+
+/// Render both bodies of a draft the way subscribers will see them.
+///
+/// The HTML body goes in a sandboxed `iframe` so that it can neither run scripts
+/// nor inherit the styles of the admin page.
+#[tracing::instrument(name = "Preview a draft newsletter issue", skip(pool))]
+pub async fn preview_draft(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let draft = get_draft(&pool, newsletter_issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("There is no draft with the provided id."))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Preview: {title}</title>
+</head>
+<body>
+    <h1>{title}</h1>
+    <h2>HTML</h2>
+    <iframe sandbox title="HTML body" srcdoc="{html_content}"></iframe>
+    <h2>Plain text</h2>
+    <pre>{text_content}</pre>
+    <p><a href="/admin/drafts/{newsletter_issue_id}">&lt;- Back</a></p>
+</body>
+</html>"#,
+            title = encode_minimal(&draft.title),
+            html_content = encode_attribute(&draft.html_content),
+            text_content = encode_minimal(&draft.text_content),
+        )))
+}
@@ -0,0 +1,161 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::super::post::{parse_send_at, release_issue, success_message, validate_templates};
+use crate::{
+    authentication::UserId,
+    domain::IssueStatus,
+    idempotency::{save_response, try_processing, IdempotencyKey, NextAction},
+    utils::{e400, e500, see_other},
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    idempotency_key: String,
+    /// When to send the issue; empty to send it right away.
+    #[serde(default)]
+    send_at: String,
+    /// Set by the checkbox to keep the issue out of the public archive.
+    hide_from_archive: Option<String>,
+}
+
+#[tracing::instrument(name = "Publish a draft newsletter issue", skip(form, pool))]
+pub async fn publish_draft(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let FormData {
+        idempotency_key,
+        send_at,
+        hide_from_archive,
+    } = form.0;
+    let hidden_from_archive = hide_from_archive.is_some();
+    let idempotency_key = IdempotencyKey::try_from(idempotency_key).map_err(e400)?;
+    let send_at = parse_send_at(&send_at, Utc::now()).map_err(e400)?;
+    let user_id = user_id.into_inner();
+    let mut transaction = match try_processing(&pool, &idempotency_key, &user_id)
+        .await
+        .map_err(e500)?
+    {
+        NextAction::StartProcessing(transaction) => transaction,
+        NextAction::ReturnSavedResponse(saved_response) => {
+            success_message(send_at).send();
+            return Ok(saved_response);
+        }
+    };
+    // Locked until the transaction commits, so the content validated here is the one published
+    if let Some(draft) = lock_draft_content(&mut transaction, newsletter_issue_id)
+        .await
+        .context("Failed to retrieve a draft newsletter issue")
+        .map_err(e500)?
+    {
+        if let Err(e) = validate_templates(
+            &draft.html_content,
+            &draft.text_content,
+            hidden_from_archive,
+        ) {
+            // Dropping the transaction rolls back the idempotency key, so the admin can retry
+            FlashMessage::error(e).send();
+            return Ok(see_other(&format!("/admin/drafts/{newsletter_issue_id}")));
+        }
+    }
+    let published = mark_draft_as_published(
+        &mut transaction,
+        newsletter_issue_id,
+        send_at,
+        hidden_from_archive,
+    )
+    .await
+    .context("Failed to publish a draft")
+    .map_err(e500)?;
+    if !published {
+        let response = see_other("/admin/drafts");
+        let response = save_response(transaction, &idempotency_key, &user_id, response)
+            .await
+            .map_err(e500)?;
+        FlashMessage::error("Only drafts can be published.").send();
+        return Ok(response);
+    }
+    release_issue(&mut transaction, newsletter_issue_id, send_at)
+        .await
+        .map_err(e500)?;
+    let response = see_other(&format!("/admin/newsletters/{newsletter_issue_id}"));
+    let response = save_response(transaction, &idempotency_key, &user_id, response)
+        .await
+        .map_err(e500)?;
+    success_message(send_at).send();
+
+    Ok(response)
+}
+
+struct DraftContent {
+    html_content: String,
+    text_content: String,
+}
+
+/// Lock a draft's row, so it cannot be edited before the transaction commits.
+#[tracing::instrument(skip(transaction))]
+async fn lock_draft_content(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<DraftContent>, sqlx::Error> {
+    sqlx::query_as!(
+        DraftContent,
+        r#"
+        SELECT html_content, text_content
+        FROM newsletter_issues
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        FOR UPDATE
+        "#,
+        newsletter_issue_id,
+        IssueStatus::Draft.as_str()
+    )
+    .fetch_optional(transaction.as_mut())
+    .await
+}
+
+/// Returns `false` if the issue is not a draft, e.g. because it has already been published.
+#[tracing::instrument(skip(transaction))]
+async fn mark_draft_as_published(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    send_at: Option<DateTime<Utc>>,
+    hidden_from_archive: bool,
+) -> Result<bool, sqlx::Error> {
+    let status = match send_at {
+        None => IssueStatus::Published,
+        Some(_) => IssueStatus::Scheduled,
+    };
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            status = $3,
+            scheduled_for = $4,
+            published_at = CASE WHEN $4::timestamptz IS NULL THEN now() END,
+            hidden_from_archive = $5
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        "#,
+        newsletter_issue_id,
+        IssueStatus::Draft.as_str(),
+        status.as_str(),
+        send_at,
+        hidden_from_archive
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+
+    Ok(n_updated_rows > 0)
+}
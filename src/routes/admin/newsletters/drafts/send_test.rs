@@ -0,0 +1,95 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::get_draft;
+use crate::{
+    authentication::UserId,
+    domain::{IssueTemplate, SubscriberEmail, TemplateContext, UnsubscribeToken},
+    email_client::EmailClient,
+    issue_delivery_worker::{unsubscribe_url, view_online_url},
+    routes::admin::{dashboard::get_username, email::get_account_email},
+    startup::{ApplicationBaseUrl, HmacSecret},
+    utils::{e404, e500, see_other},
+};
+
+/// Send a draft to the logged-in admin only, so they can check how it renders in a real inbox.
+#[tracing::instrument(
+    name = "Send a test email for a draft newsletter issue",
+    skip(pool, email_client, base_url, hmac_secret)
+)]
+pub async fn send_test_email(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    hmac_secret: web::Data<HmacSecret>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let draft_url = format!("/admin/drafts/{newsletter_issue_id}");
+    let draft = get_draft(&pool, newsletter_issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("There is no draft with the provided id."))?;
+    let recipient = get_account_email(&pool, user_id)
+        .await
+        .map_err(e500)?
+        .map(SubscriberEmail::parse)
+        .transpose()
+        .map_err(e500)?;
+    let Some(recipient) = recipient else {
+        FlashMessage::error(
+            r#"Set your <a href="/admin/email">email address</a> before sending a test."#,
+        )
+        .send();
+        return Ok(see_other(&draft_url));
+    };
+    let templates = IssueTemplate::parse(&draft.html_content)
+        .and_then(|html| Ok((html, IssueTemplate::parse(&draft.text_content)?)));
+    let (html, text) = match templates {
+        Ok(templates) => templates,
+        Err(e) => {
+            FlashMessage::error(format!("The draft is not a valid template. {e}")).send();
+            return Ok(see_other(&draft_url));
+        }
+    };
+    // Tokens are per subscriber: the admin gets a valid one for a subscriber that does not
+    // exist, so the link leads to the same page as in real issues without affecting anyone
+    let unsubscribe_token = UnsubscribeToken::generate(Uuid::nil(), &hmac_secret.0);
+    let unsubscribe_url = unsubscribe_url(&base_url.0, &unsubscribe_token);
+    let view_online_url = view_online_url(&base_url.0, newsletter_issue_id);
+    let username = get_username(user_id.0, &pool).await.map_err(e500)?;
+    let context = TemplateContext {
+        subscriber_name: &username,
+        subscriber_email: recipient.as_ref(),
+        unsubscribe_url: &unsubscribe_url,
+        view_online_url: &view_online_url,
+    };
+    if let Err(e) = email_client
+        .send_email(
+            &recipient,
+            &format!("[Test] {}", draft.title),
+            &html.render_html(&context),
+            &text.render_text(&context),
+        )
+        .await
+    {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to send a test email"
+        );
+        FlashMessage::error("Failed to send the test email.").send();
+        return Ok(see_other(&draft_url));
+    }
+    FlashMessage::info(format!(
+        "A test email has been sent to {}.",
+        htmlescape::encode_minimal(recipient.as_ref())
+    ))
+    .send();
+
+    Ok(see_other(&draft_url))
+}
@@ -27,25 +27,40 @@ pub async fn publish_newsletter_form(flash_messages: IncomingFlashMessages) -> H
             >
         </label>
         <br>
+        <label>Markdown Content (generates both bodies below)
+            <textarea name="markdown_content"></textarea>
+        </label>
+        <br>
         <label>HTML Content
-            <input
-                type="text"
+            <textarea
                 placeholder="Enter Content in HTML"
                 name="html_content"
-            >
+            ></textarea>
         </label>
         <br>
         <label>Text Content
-            <input
-                type="text"
+            <textarea
                 placeholder="Enter Content in Plain Text"
                 name="text_content"
+            ></textarea>
+        </label>
+        <br>
+        <label>Send at (UTC, leave empty to send right away)
+            <input
+                type="datetime-local"
+                name="send_at"
             >
         </label>
         <br>
+        <label>
+            <input type="checkbox" name="hide_from_archive">
+            Hide from the public archive
+        </label>
+        <br>
         <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
         <button type="submit">Submit</button>
     </form>
+    <p><a href="/admin/issues">Past issues</a></p>
     <p><a href="/admin/dashboard">&lt;- Back</a></p>
 </body>
 </html>"#,
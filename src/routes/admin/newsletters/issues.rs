@@ -0,0 +1,248 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::super::pagination::Cursor;
+use crate::{
+    domain::DeliveryStatus,
+    utils::{e400, e500},
+};
+
+const ISSUES_PER_PAGE: i64 = 20;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Filters {
+    /// `YYYY-MM-DD`, inclusive; empty for no lower bound.
+    #[serde(default)]
+    published_from: String,
+    /// `YYYY-MM-DD`, inclusive; empty for no upper bound.
+    #[serde(default)]
+    published_until: String,
+    /// Matched case-insensitively against titles and plain-text bodies; empty to match everything.
+    #[serde(default)]
+    q: String,
+    /// Where the previous page stopped; empty for the first page.
+    #[serde(default)]
+    after: String,
+}
+
+struct PublishedIssue {
+    newsletter_issue_id: Uuid,
+    title: String,
+    status: String,
+    published_at: DateTime<Utc>,
+    n_recipients: i64,
+    n_sent: i64,
+    n_pending: i64,
+}
+
+#[tracing::instrument(name = "List published newsletter issues", skip(pool))]
+pub async fn newsletter_issues(
+    filters: web::Query<Filters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let published_from = parse_date(&filters.published_from).map_err(e400)?;
+    let published_until = parse_date(&filters.published_until).map_err(e400)?;
+    let after = Cursor::parse(&filters.after).map_err(e400)?;
+    let search = filters.q.trim();
+    // Both bounds are whole days, in UTC
+    let published_after = published_from.map(|d| d.and_time(Default::default()).and_utc());
+    let published_before = published_until
+        .and_then(|d| d.checked_add_days(Days::new(1)))
+        .map(|d| d.and_time(Default::default()).and_utc());
+    let mut issues = get_published_issues(
+        &pool,
+        published_after,
+        published_before,
+        search,
+        after.as_ref(),
+    )
+    .await
+    .map_err(e500)?;
+    // One extra issue is fetched to know whether there is a next page
+    let next_page = if issues.len() as i64 > ISSUES_PER_PAGE {
+        issues.truncate(ISSUES_PER_PAGE as usize);
+        issues.last().map(|i| Cursor {
+            timestamp: i.published_at,
+            id: i.newsletter_issue_id,
+        })
+    } else {
+        None
+    };
+
+    let mut rows_html = String::new();
+    for issue in &issues {
+        writeln!(
+            rows_html,
+            r#"        <tr>
+            <td><a href="/admin/newsletters/{id}">{title}</a></td>
+            <td>{published_at}</td>
+            <td>{status}</td>
+            <td>{n_recipients}</td>
+            <td>{n_sent} sent, {n_pending} pending</td>
+        </tr>"#,
+            id = issue.newsletter_issue_id,
+            title = encode_minimal(&issue.title),
+            published_at = issue.published_at.to_rfc3339(),
+            status = encode_minimal(&issue.status),
+            n_recipients = issue.n_recipients,
+            n_sent = issue.n_sent,
+            n_pending = issue.n_pending,
+        )
+        .unwrap();
+    }
+    let published_from = published_from.map(|d| d.to_string()).unwrap_or_default();
+    let published_until = published_until.map(|d| d.to_string()).unwrap_or_default();
+    let filters_query = format!(
+        "published_from={published_from}&published_until={published_until}&q={}",
+        urlencoding::encode(search)
+    );
+    let mut pages_html = String::new();
+    if after.is_some() {
+        write!(
+            pages_html,
+            r#"<a href="/admin/issues?{}">Newest issues</a> "#,
+            encode_minimal(&filters_query)
+        )
+        .unwrap();
+    }
+    if let Some(next_page) = next_page {
+        write!(
+            pages_html,
+            r#"<a href="/admin/issues?{}&amp;after={next_page}">Older issues</a>"#,
+            encode_minimal(&filters_query)
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Published issues</title>
+</head>
+<body>
+    <form action="/admin/issues" method="get">
+        <label>Search
+            <input type="search" name="q" value="{search}">
+        </label>
+        <label>Published from
+            <input type="date" name="published_from" value="{published_from}">
+        </label>
+        <label>until
+            <input type="date" name="published_until" value="{published_until}">
+        </label>
+        <button type="submit">Filter</button>
+    </form>
+    <table>
+        <tr><th>Title</th><th>Published at</th><th>Status</th><th>Recipients</th><th>Progress</th></tr>
+{rows_html}
+    </table>
+    <p>{pages_html}</p>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+            search = encode_attribute(search),
+        )))
+}
+
+/// Parse an optional date, as sent by `<input type="date">`.
+fn parse_date(date: &str) -> Result<Option<NaiveDate>, anyhow::Error> {
+    let date = date.trim();
+    if date.is_empty() {
+        return Ok(None);
+    }
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .with_context(|| format!("`{date}` is not a valid date."))?;
+
+    Ok(Some(date))
+}
+
+/// A page of published issues, newest first, optionally within `[published_after, published_before)`.
+/// `search` matches the title, the plain-text body and, for issues written in Markdown, the source.
+#[tracing::instrument(skip(pool))]
+async fn get_published_issues(
+    pool: &PgPool,
+    published_after: Option<DateTime<Utc>>,
+    published_before: Option<DateTime<Utc>>,
+    search: &str,
+    after: Option<&Cursor>,
+) -> Result<Vec<PublishedIssue>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        PublishedIssue,
+        r#"
+        SELECT
+            i.newsletter_issue_id,
+            i.title,
+            i.status,
+            i.published_at AS "published_at!",
+            count(d.subscriber_email) AS "n_recipients!",
+            count(d.subscriber_email) FILTER (WHERE d.status = $6) AS "n_sent!",
+            count(d.subscriber_email) FILTER (WHERE d.status = $7) AS "n_pending!"
+        FROM newsletter_issues i
+        LEFT JOIN issue_deliveries d USING (newsletter_issue_id)
+        WHERE
+            i.published_at IS NOT NULL AND
+            ($1::timestamptz IS NULL OR i.published_at >= $1) AND
+            ($2::timestamptz IS NULL OR i.published_at < $2) AND
+            (
+                $3 = '' OR
+                strpos(lower(i.title), lower($3)) > 0 OR
+                strpos(lower(i.text_content), lower($3)) > 0 OR
+                strpos(lower(i.markdown_content), lower($3)) > 0
+            ) AND
+            ($4::timestamptz IS NULL OR (i.published_at, i.newsletter_issue_id) < ($4, $5))
+        GROUP BY i.newsletter_issue_id
+        ORDER BY i.published_at DESC, i.newsletter_issue_id DESC
+        LIMIT $8
+        "#,
+        published_after,
+        published_before,
+        search,
+        after.map(|c| c.timestamp),
+        after.map(|c| c.id),
+        DeliveryStatus::Sent.as_str(),
+        DeliveryStatus::Queued.as_str(),
+        ISSUES_PER_PAGE + 1
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve published newsletter issues.")?;
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use claims::{assert_err, assert_ok_eq};
+
+    use super::parse_date;
+
+    #[test]
+    fn an_empty_date_means_no_bound() {
+        assert_ok_eq!(parse_date(""), None);
+        assert_ok_eq!(parse_date(" "), None);
+    }
+
+    #[test]
+    fn dates_are_parsed() {
+        assert_ok_eq!(
+            parse_date("2023-11-13"),
+            Some(NaiveDate::from_ymd_opt(2023, 11, 13).unwrap())
+        );
+    }
+
+    #[test]
+    fn garbage_dates_are_rejected() {
+        assert_err!(parse_date("13/11/2023"));
+        assert_err!(parse_date("2023-02-30"));
+    }
+}
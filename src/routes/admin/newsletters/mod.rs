@@ -1,4 +1,16 @@
+mod cancel;
+pub use cancel::cancel_newsletter_issue;
+mod detail;
+pub use detail::newsletter_issue_detail;
+mod drafts;
+pub use drafts::{
+    create_draft, draft_form, drafts, preview_draft, publish_draft, send_test_email, update_draft,
+};
 mod get;
 pub use get::publish_newsletter_form;
+mod issues;
+pub use issues::newsletter_issues;
+mod pause;
+pub use pause::{pause_newsletter_issue, resume_newsletter_issue};
 mod post;
 pub use post::publish_newsletter;
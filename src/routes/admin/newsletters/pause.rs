@@ -0,0 +1,95 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::IssueStatus,
+    issue_delivery_worker::notify_workers,
+    utils::{e500, see_other},
+};
+
+#[tracing::instrument(name = "Pause the delivery of a newsletter issue", skip(pool))]
+pub async fn pause_newsletter_issue(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let paused = set_status(
+        &pool,
+        newsletter_issue_id,
+        IssueStatus::Published,
+        IssueStatus::Paused,
+    )
+    .await
+    .context("Failed to pause a newsletter issue")
+    .map_err(e500)?;
+    if paused {
+        FlashMessage::info("The delivery has been paused.").send();
+    } else {
+        FlashMessage::error("Only issues being delivered can be paused.").send();
+    }
+
+    Ok(see_other(&format!(
+        "/admin/newsletters/{newsletter_issue_id}"
+    )))
+}
+
+#[tracing::instrument(name = "Resume the delivery of a newsletter issue", skip(pool))]
+pub async fn resume_newsletter_issue(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let resumed = set_status(
+        &pool,
+        newsletter_issue_id,
+        IssueStatus::Paused,
+        IssueStatus::Published,
+    )
+    .await
+    .context("Failed to resume a newsletter issue")
+    .map_err(e500)?;
+    if resumed {
+        FlashMessage::info("The delivery has been resumed.").send();
+    } else {
+        FlashMessage::error("Only paused issues can be resumed.").send();
+    }
+
+    Ok(see_other(&format!(
+        "/admin/newsletters/{newsletter_issue_id}"
+    )))
+}
+
+/// Move an issue from `from` to `to`, waking up the workers in case its tasks became eligible.
+///
+/// Returns `false` if the issue was not in the `from` state.
+#[tracing::instrument(skip(pool))]
+async fn set_status(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    from: IssueStatus,
+    to: IssueStatus,
+) -> Result<bool, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $3
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        "#,
+        newsletter_issue_id,
+        from.as_str(),
+        to.as_str()
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    notify_workers(&mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(n_updated_rows > 0)
+}
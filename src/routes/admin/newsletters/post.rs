@@ -1,19 +1,29 @@
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 use crate::authentication::UserId;
+use crate::domain::{render_markdown, IssueStatus, IssueTemplate};
 use crate::idempotency::{save_response, try_processing, IdempotencyKey, NextAction};
+use crate::issue_delivery_worker::{enqueue_delivery_tasks, notify_workers};
 use crate::utils::{e400, e500, see_other};
 
 #[derive(serde::Deserialize)]
 pub struct FormData {
     title: String,
-    html_content: String,
-    text_content: String,
+    html_content: Option<String>,
+    text_content: Option<String>,
+    /// When set, both bodies are generated from it.
+    markdown_content: Option<String>,
     idempotency_key: String,
+    /// When to send the issue; empty to send it right away.
+    #[serde(default)]
+    send_at: String,
+    /// Set by the checkbox to keep the issue out of the public archive.
+    hide_from_archive: Option<String>,
 }
 
 #[tracing::instrument(name = "Publish a newsletter issue", skip(form, pool))]
@@ -26,9 +36,21 @@ pub async fn publish_newsletter(
         title,
         html_content,
         text_content,
+        markdown_content,
         idempotency_key,
+        send_at,
+        hide_from_archive,
     } = form.0;
+    let hidden_from_archive = hide_from_archive.is_some();
     let idempotency_key = IdempotencyKey::try_from(idempotency_key).map_err(e400)?;
+    let send_at = parse_send_at(&send_at, Utc::now()).map_err(e400)?;
+    let content = IssueContent::new(html_content, text_content, markdown_content).map_err(e400)?;
+    validate_templates(
+        &content.html_content,
+        &content.text_content,
+        hidden_from_archive,
+    )
+    .map_err(e400)?;
     let user_id = user_id.into_inner();
     let mut transaction = match try_processing(&pool, &idempotency_key, &user_id)
         .await
@@ -36,39 +58,160 @@ pub async fn publish_newsletter(
     {
         NextAction::StartProcessing(transaction) => transaction,
         NextAction::ReturnSavedResponse(saved_response) => {
-            success_message().send();
+            success_message(send_at).send();
             return Ok(saved_response);
         }
     };
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
-        .await
-        .context("Failed to store newsletter issue details")
-        .map_err(e500)?;
-    enqueue_delivery_tasks(&mut transaction, issue_id)
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        &title,
+        &content,
+        send_at,
+        hidden_from_archive,
+    )
+    .await
+    .context("Failed to store newsletter issue details")
+    .map_err(e500)?;
+    release_issue(&mut transaction, issue_id, send_at)
         .await
-        .context("Failed to enqueue delivery tasks")
         .map_err(e500)?;
     let response = see_other("/admin/newsletters");
     let response = save_response(transaction, &idempotency_key, &user_id, response)
         .await
         .map_err(e500)?;
-    success_message().send();
+    success_message(send_at).send();
+    FlashMessage::info(format!(
+        r#"<a href="/admin/newsletters/{issue_id}">Track delivery progress</a>"#
+    ))
+    .send();
 
     Ok(response)
 }
 
-fn success_message() -> FlashMessage {
-    FlashMessage::info("The newsletter issue has been published!")
+/// Hand a freshly published issue over to the delivery workers: straight away,
+/// or once `send_at` arrives.
+pub(super) async fn release_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    send_at: Option<DateTime<Utc>>,
+) -> Result<(), anyhow::Error> {
+    if send_at.is_none() {
+        enqueue_delivery_tasks(transaction, issue_id)
+            .await
+            .context("Failed to enqueue delivery tasks")?;
+    } else {
+        // Let idle workers know when to wake up for it
+        notify_workers(transaction)
+            .await
+            .context("Failed to notify delivery workers")?;
+    }
+
+    Ok(())
+}
+
+pub(super) fn success_message(send_at: Option<DateTime<Utc>>) -> FlashMessage {
+    match send_at {
+        None => FlashMessage::info("The newsletter issue has been published!"),
+        Some(send_at) => FlashMessage::info(format!(
+            "The newsletter issue has been scheduled for {}.",
+            send_at.to_rfc3339()
+        )),
+    }
+}
+
+/// The bodies of an issue, either typed in as-is or generated from Markdown.
+#[derive(Debug)]
+pub(super) struct IssueContent {
+    pub(super) html_content: String,
+    pub(super) text_content: String,
+    pub(super) markdown_content: Option<String>,
+}
+
+impl IssueContent {
+    /// Markdown wins over hand-written bodies; without it, both bodies are required.
+    pub(super) fn new(
+        html_content: Option<String>,
+        text_content: Option<String>,
+        markdown_content: Option<String>,
+    ) -> Result<Self, anyhow::Error> {
+        if let Some(markdown_content) = markdown_content.filter(|m| !m.trim().is_empty()) {
+            let rendered = render_markdown(&markdown_content);
+            return Ok(Self {
+                html_content: rendered.html,
+                text_content: rendered.text,
+                markdown_content: Some(markdown_content),
+            });
+        }
+        match (html_content, text_content) {
+            (Some(html_content), Some(text_content)) => Ok(Self {
+                html_content,
+                text_content,
+                markdown_content: None,
+            }),
+            _ => {
+                anyhow::bail!("Either Markdown content or both HTML and text content are required.")
+            }
+        }
+    }
+}
+
+/// Reject bodies whose placeholders could not be rendered, before anything is sent.
+///
+/// Issues hidden from the archive cannot be viewed online, so they cannot link there either.
+pub(super) fn validate_templates(
+    html_content: &str,
+    text_content: &str,
+    hidden_from_archive: bool,
+) -> Result<(), String> {
+    let html = IssueTemplate::parse(html_content)
+        .map_err(|e| format!("The HTML content is not a valid template. {e}"))?;
+    let text = IssueTemplate::parse(text_content)
+        .map_err(|e| format!("The text content is not a valid template. {e}"))?;
+    if hidden_from_archive && (html.links_online_version() || text.links_online_version()) {
+        return Err(
+            "Issues hidden from the archive cannot use `{{ view_online_url }}`.".to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Parse the optional send time of an issue.
+///
+/// Accepts RFC 3339 timestamps as well as the zone-less `YYYY-MM-DDTHH:MM` produced by
+/// `<input type="datetime-local">`, which is taken to be UTC.
+pub(super) fn parse_send_at(
+    send_at: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+    let send_at = send_at.trim();
+    if send_at.is_empty() {
+        return Ok(None);
+    }
+    let send_at = DateTime::parse_from_rfc3339(send_at)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(send_at, "%Y-%m-%dT%H:%M").map(|t| t.and_utc()))
+        .with_context(|| format!("`{send_at}` is not a valid send time."))?;
+    if send_at <= now {
+        anyhow::bail!("The send time must be in the future.");
+    }
+
+    Ok(Some(send_at))
 }
 
 #[tracing::instrument(skip_all)]
 async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
     title: &str,
-    text_content: &str,
-    html_content: &str,
+    content: &IssueContent,
+    send_at: Option<DateTime<Utc>>,
+    hidden_from_archive: bool,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
+    let status = match send_at {
+        None => IssueStatus::Published,
+        Some(_) => IssueStatus::Scheduled,
+    };
     sqlx::query!(
         r#"
         INSERT INTO newsletter_issues (
@@ -76,14 +219,22 @@ async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
-            published_at
+            markdown_content,
+            published_at,
+            status,
+            scheduled_for,
+            hidden_from_archive
         )
-        VALUES ($1, $2, $3, $4, now())
+        VALUES ($1, $2, $3, $4, $5, CASE WHEN $7::timestamptz IS NULL THEN now() END, $6, $7, $8)
         "#,
         newsletter_issue_id,
         title,
-        text_content,
-        html_content
+        content.text_content,
+        content.html_content,
+        content.markdown_content,
+        status.as_str(),
+        send_at,
+        hidden_from_archive
     )
     .execute(transaction.as_mut())
     .await?;
@@ -91,25 +242,71 @@ async fn insert_newsletter_issue(
     Ok(newsletter_issue_id)
 }
 
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
-    transaction: &mut Transaction<'_, Postgres>,
-    newsletter_issue_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"
-        INSERT INTO issue_delivery_queue (
-            newsletter_issue_id,
-            subscriber_email
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use claims::{assert_err, assert_none, assert_ok, assert_ok_eq};
+
+    use super::{parse_send_at, IssueContent};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 11, 6, 9, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn an_empty_send_time_means_right_away() {
+        assert_none!(parse_send_at("", now()).unwrap());
+        assert_none!(parse_send_at("  ", now()).unwrap());
+    }
+
+    #[test]
+    fn rfc3339_send_times_are_accepted() {
+        let expected = Utc.with_ymd_and_hms(2023, 11, 6, 10, 30, 0).unwrap();
+        assert_ok_eq!(
+            parse_send_at("2023-11-06T12:30:00+02:00", now()),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn datetime_local_send_times_are_taken_as_utc() {
+        let expected = Utc.with_ymd_and_hms(2023, 11, 6, 10, 30, 0).unwrap();
+        assert_ok_eq!(parse_send_at("2023-11-06T10:30", now()), Some(expected));
+    }
+
+    #[test]
+    fn send_times_in_the_past_are_rejected() {
+        assert_err!(parse_send_at("2023-11-06T08:59", now()));
+        assert_err!(parse_send_at("2023-11-06T09:00", now()));
+    }
+
+    #[test]
+    fn garbage_send_times_are_rejected() {
+        assert_err!(parse_send_at("tomorrow", now()));
+    }
+
+    #[test]
+    fn markdown_content_generates_both_bodies() {
+        let content = IssueContent::new(
+            Some("<p>ignored</p>".into()),
+            None,
+            Some("Hello *world*".into()),
         )
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
-        "#,
-        newsletter_issue_id
-    )
-    .execute(transaction.as_mut())
-    .await?;
+        .unwrap();
+        assert_eq!(content.html_content, "<p>Hello <em>world</em></p>\n");
+        assert_eq!(content.text_content, "Hello world");
+        assert_eq!(content.markdown_content.as_deref(), Some("Hello *world*"));
+    }
 
-    Ok(())
+    #[test]
+    fn without_markdown_both_bodies_are_required() {
+        assert_err!(IssueContent::new(Some("<p>Hi</p>".into()), None, None));
+        assert_err!(IssueContent::new(None, Some("Hi".into()), Some(" ".into())));
+        let content = assert_ok!(IssueContent::new(
+            Some("<p>Hi</p>".into()),
+            Some("Hi".into()),
+            Some(String::new())
+        ));
+        assert_none!(content.markdown_content);
+    }
 }
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Where a page of a keyset-paginated list stops.
+///
+/// Lists are ordered by a timestamp then an id, both descending: the next page holds the rows
+/// that compare lower than `(timestamp, id)`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    /// `{microseconds since the epoch}_{id}`: URL-safe, and as precise as Postgres timestamps.
+    pub fn parse(cursor: &str) -> Result<Option<Self>, anyhow::Error> {
+        let cursor = cursor.trim();
+        if cursor.is_empty() {
+            return Ok(None);
+        }
+        let invalid = || anyhow::anyhow!("`{cursor}` is not a valid page cursor.");
+        let (micros, id) = cursor.split_once('_').ok_or_else(invalid)?;
+        let micros: i64 = micros.parse().map_err(|_| invalid())?;
+        let timestamp = DateTime::from_timestamp(
+            micros.div_euclid(1_000_000),
+            (micros.rem_euclid(1_000_000) * 1_000) as u32,
+        )
+        .ok_or_else(invalid)?;
+        let id = id.parse().map_err(|_| invalid())?;
+
+        Ok(Some(Self { timestamp, id }))
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.timestamp.timestamp_micros(), self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use claims::{assert_err, assert_ok_eq};
+    use uuid::Uuid;
+
+    use super::Cursor;
+
+    #[test]
+    fn cursors_round_trip_with_microsecond_precision() {
+        let cursor = Cursor {
+            timestamp: Utc.timestamp_opt(1_699_844_478, 123_456_000).unwrap(),
+            id: Uuid::new_v4(),
+        };
+        assert_ok_eq!(Cursor::parse(&cursor.to_string()), Some(cursor));
+        assert_ok_eq!(Cursor::parse(""), None);
+    }
+
+    #[test]
+    fn garbage_cursors_are_rejected() {
+        assert_err!(Cursor::parse("yesterday"));
+        assert_err!(Cursor::parse("12_not-a-uuid"));
+        assert_err!(Cursor::parse(&format!("soon_{}", Uuid::new_v4())));
+    }
+}
@@ -0,0 +1,151 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    routes::{
+        subscriptions_confirm::mark_as_confirmed,
+        subscriptions_unsubscribe::{drop_pending_deliveries, unsubscribe_subscriber},
+    },
+    utils::{e500, see_other},
+    welcome_email::enqueue_welcome_email,
+};
+
+/// Confirm a subscriber on their behalf, e.g. when the confirmation email never arrived.
+#[tracing::instrument(name = "Manually confirm a subscriber", skip(pool))]
+pub async fn confirm_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    let confirmed = mark_as_confirmed(transaction.as_mut(), subscriber_id)
+        .await
+        .context("Failed to confirm a subscriber")
+        .map_err(e500)?;
+    if confirmed {
+        enqueue_welcome_email(&mut transaction, subscriber_id)
+            .await
+            .context("Failed to enqueue a welcome email")
+            .map_err(e500)?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to confirm a subscriber.")
+        .map_err(e500)?;
+    if confirmed {
+        FlashMessage::info("The subscriber has been confirmed.").send();
+    } else {
+        FlashMessage::error("Only subscribers pending confirmation can be confirmed.").send();
+    }
+
+    Ok(see_other(&format!("/admin/subscribers/{subscriber_id}")))
+}
+
+#[tracing::instrument(name = "Manually unsubscribe a subscriber", skip(pool))]
+pub async fn cancel_subscription(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    let email = unsubscribe_subscriber(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to update subscriber status to `unsubscribed`")
+        .map_err(e500)?;
+    let Some(email) = email else {
+        FlashMessage::error("There is no subscriber with the provided id.").send();
+        return Ok(see_other("/admin/subscribers"));
+    };
+    drop_pending_deliveries(&mut transaction, &email)
+        .await
+        .context("Failed to remove pending deliveries for an unsubscribed subscriber")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to unsubscribe a subscriber.")
+        .map_err(e500)?;
+    FlashMessage::info("The subscriber has been unsubscribed.").send();
+
+    Ok(see_other(&format!("/admin/subscribers/{subscriber_id}")))
+}
+
+/// Remove every trace of a subscriber but their past deliveries.
+#[tracing::instrument(name = "Delete a subscriber", skip(pool))]
+pub async fn delete_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    let email = delete(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to delete a subscriber")
+        .map_err(e500)?;
+    let Some(email) = email else {
+        FlashMessage::error("There is no subscriber with the provided id.").send();
+        return Ok(see_other("/admin/subscribers"));
+    };
+    drop_pending_deliveries(&mut transaction, &email)
+        .await
+        .context("Failed to remove pending deliveries for a deleted subscriber")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to delete a subscriber.")
+        .map_err(e500)?;
+    FlashMessage::info(format!(
+        "{} has been deleted.",
+        htmlescape::encode_minimal(&email)
+    ))
+    .send();
+
+    Ok(see_other("/admin/subscribers"))
+}
+
+/// Returns the email address of the deleted subscriber, if there was one.
+#[tracing::instrument(skip(transaction))]
+async fn delete(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens
+        WHERE subscriber_id = $1
+        "#,
+        subscriber_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    let email = sqlx::query!(
+        r#"
+        DELETE FROM subscriptions
+        WHERE id = $1
+        RETURNING email
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?
+    .map(|r| r.email);
+
+    Ok(email)
+}
@@ -0,0 +1,196 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::{
+    domain::SubscriptionStatus,
+    utils::{e404, e500},
+};
+
+struct Subscriber {
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    confirmed_at: Option<DateTime<Utc>>,
+    unsubscribed_at: Option<DateTime<Utc>>,
+    n_confirmation_emails: i64,
+}
+
+struct Delivery {
+    newsletter_issue_id: Uuid,
+    title: String,
+    status: String,
+    updated_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Show a subscriber", skip(pool, flash_messages))]
+pub async fn subscriber_detail(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let subscriber = get_subscriber(&pool, subscriber_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("There is no subscriber with the provided id."))?;
+    let deliveries = get_deliveries(&pool, &subscriber.email)
+        .await
+        .map_err(e500)?;
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let mut history_html = format!(
+        "<li>Subscribed at {}</li>\n        <li>{} confirmation email(s) sent</li>",
+        subscriber.subscribed_at.to_rfc3339(),
+        subscriber.n_confirmation_emails,
+    );
+    if let Some(confirmed_at) = subscriber.confirmed_at {
+        write!(
+            history_html,
+            "\n        <li>Confirmed at {}</li>",
+            confirmed_at.to_rfc3339()
+        )
+        .unwrap();
+    }
+    if let Some(unsubscribed_at) = subscriber.unsubscribed_at {
+        write!(
+            history_html,
+            "\n        <li>Unsubscribed at {}</li>",
+            unsubscribed_at.to_rfc3339()
+        )
+        .unwrap();
+    }
+    let mut deliveries_html = String::new();
+    for d in &deliveries {
+        writeln!(
+            deliveries_html,
+            r#"        <tr>
+            <td><a href="/admin/newsletters/{id}">{title}</a></td>
+            <td>{status}</td>
+            <td>{updated_at}</td>
+        </tr>"#,
+            id = d.newsletter_issue_id,
+            title = encode_minimal(&d.title),
+            status = encode_minimal(&d.status),
+            updated_at = d.updated_at.to_rfc3339(),
+        )
+        .unwrap();
+    }
+    let mut actions_html = String::new();
+    if subscriber.status == SubscriptionStatus::PendingConfirmation.as_str() {
+        writeln!(
+            actions_html,
+            r#"    <form action="/admin/subscribers/{subscriber_id}/confirm" method="post">
+        <button type="submit">Confirm</button>
+    </form>"#
+        )
+        .unwrap();
+    }
+    if subscriber.status != SubscriptionStatus::Unsubscribed.as_str() {
+        writeln!(
+            actions_html,
+            r#"    <form action="/admin/subscribers/{subscriber_id}/unsubscribe" method="post">
+        <button type="submit">Unsubscribe</button>
+    </form>"#
+        )
+        .unwrap();
+    }
+    write!(
+        actions_html,
+        r#"    <form action="/admin/subscribers/{subscriber_id}/delete" method="post">
+        <button type="submit">Delete</button>
+    </form>"#
+    )
+    .unwrap();
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{email}</title>
+</head>
+<body>
+    {msg_html}
+    <h1>{email}</h1>
+    <p>{name}, {status}</p>
+    <ul>
+        {history_html}
+    </ul>
+{actions_html}
+    <h2>Deliveries</h2>
+    <table>
+        <tr><th>Issue</th><th>Status</th><th>Updated at</th></tr>
+{deliveries_html}
+    </table>
+    <p><a href="/admin/subscribers">&lt;- Back</a></p>
+</body>
+</html>"#,
+            email = encode_minimal(&subscriber.email),
+            name = encode_minimal(&subscriber.name),
+            status = encode_minimal(&subscriber.status),
+        )))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT
+            email,
+            name,
+            status,
+            subscribed_at,
+            confirmed_at,
+            unsubscribed_at,
+            (
+                SELECT count(*)
+                FROM subscription_tokens
+                WHERE subscriber_id = id
+            ) AS "n_confirmation_emails!"
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve a subscriber.")?;
+
+    Ok(subscriber)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_deliveries(pool: &PgPool, email: &str) -> Result<Vec<Delivery>, anyhow::Error> {
+    let deliveries = sqlx::query_as!(
+        Delivery,
+        r#"
+        SELECT d.newsletter_issue_id, i.title, d.status, d.updated_at
+        FROM issue_deliveries d
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        WHERE d.subscriber_email = $1
+        ORDER BY d.queued_at DESC
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the deliveries to a subscriber.")?;
+
+    Ok(deliveries)
+}
@@ -0,0 +1,187 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::super::pagination::Cursor;
+use crate::{
+    domain::SubscriptionStatus,
+    utils::{e400, e500},
+};
+
+const SUBSCRIBERS_PER_PAGE: i64 = 50;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Filters {
+    /// Matched case-insensitively against emails and names; empty to match everyone.
+    #[serde(default)]
+    q: String,
+    /// One of the `SubscriptionStatus`es; empty for all of them.
+    #[serde(default)]
+    status: String,
+    /// Where the previous page stopped; empty for the first page.
+    #[serde(default)]
+    after: String,
+}
+
+struct SubscriberSummary {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "List subscribers", skip(pool, flash_messages))]
+pub async fn subscribers(
+    filters: web::Query<Filters>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let search = filters.q.trim();
+    let status = match filters.status.trim() {
+        "" => None,
+        status => Some(SubscriptionStatus::parse(status).map_err(e400)?),
+    };
+    let after = Cursor::parse(&filters.after).map_err(e400)?;
+    let mut subscribers = get_subscribers(&pool, search, status, after.as_ref())
+        .await
+        .map_err(e500)?;
+    // One extra subscriber is fetched to know whether there is a next page
+    let next_page = if subscribers.len() as i64 > SUBSCRIBERS_PER_PAGE {
+        subscribers.truncate(SUBSCRIBERS_PER_PAGE as usize);
+        subscribers.last().map(|s| Cursor {
+            timestamp: s.subscribed_at,
+            id: s.id,
+        })
+    } else {
+        None
+    };
+
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let mut rows_html = String::new();
+    for subscriber in &subscribers {
+        writeln!(
+            rows_html,
+            r#"        <tr>
+            <td><a href="/admin/subscribers/{id}">{email}</a></td>
+            <td>{name}</td>
+            <td>{status}</td>
+            <td>{subscribed_at}</td>
+        </tr>"#,
+            id = subscriber.id,
+            email = encode_minimal(&subscriber.email),
+            name = encode_minimal(&subscriber.name),
+            status = encode_minimal(&subscriber.status),
+            subscribed_at = subscriber.subscribed_at.to_rfc3339(),
+        )
+        .unwrap();
+    }
+    let mut status_options = String::from(r#"<option value="">Any status</option>"#);
+    for s in SubscriptionStatus::ALL {
+        write!(
+            status_options,
+            r#"<option value="{value}"{selected}>{value}</option>"#,
+            value = s.as_str(),
+            selected = if status == Some(s) { " selected" } else { "" },
+        )
+        .unwrap();
+    }
+    let filters_query = format!(
+        "q={}&status={}",
+        urlencoding::encode(search),
+        status.map(|s| s.as_str()).unwrap_or_default()
+    );
+    let mut pages_html = String::new();
+    if after.is_some() {
+        write!(
+            pages_html,
+            r#"<a href="/admin/subscribers?{}">Newest subscribers</a> "#,
+            encode_minimal(&filters_query)
+        )
+        .unwrap();
+    }
+    if let Some(next_page) = next_page {
+        write!(
+            pages_html,
+            r#"<a href="/admin/subscribers?{}&amp;after={next_page}">Older subscribers</a>"#,
+            encode_minimal(&filters_query)
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Subscribers</title>
+</head>
+<body>
+    {msg_html}
+    <form action="/admin/subscribers" method="get">
+        <label>Search
+            <input type="search" name="q" value="{search}">
+        </label>
+        <label>Status
+            <select name="status">{status_options}</select>
+        </label>
+        <button type="submit">Filter</button>
+    </form>
+    <table>
+        <tr><th>Email</th><th>Name</th><th>Status</th><th>Subscribed at</th></tr>
+{rows_html}
+    </table>
+    <p>{pages_html}</p>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+            search = encode_attribute(search),
+        )))
+}
+
+/// A page of subscribers, most recent first.
+#[tracing::instrument(skip(pool))]
+async fn get_subscribers(
+    pool: &PgPool,
+    search: &str,
+    status: Option<SubscriptionStatus>,
+    after: Option<&Cursor>,
+) -> Result<Vec<SubscriberSummary>, anyhow::Error> {
+    let subscribers = sqlx::query_as!(
+        SubscriberSummary,
+        r#"
+        SELECT id, email, name, status, subscribed_at
+        FROM subscriptions
+        WHERE
+            (
+                $1 = '' OR
+                strpos(lower(email), lower($1)) > 0 OR
+                strpos(lower(name), lower($1)) > 0
+            ) AND
+            ($2::text IS NULL OR status = $2) AND
+            ($3::timestamptz IS NULL OR (subscribed_at, id) < ($3, $4))
+        ORDER BY subscribed_at DESC, id DESC
+        LIMIT $5
+        "#,
+        search,
+        status.map(|s| s.as_str()),
+        after.map(|c| c.timestamp),
+        after.map(|c| c.id),
+        SUBSCRIBERS_PER_PAGE + 1
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve subscribers.")?;
+
+    Ok(subscribers)
+}
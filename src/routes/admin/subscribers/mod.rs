@@ -0,0 +1,6 @@
+mod actions;
+pub use actions::{cancel_subscription, confirm_subscriber, delete_subscriber};
+mod detail;
+pub use detail::subscriber_detail;
+mod get;
+pub use get::subscribers;
@@ -0,0 +1,74 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::{utils::e500, welcome_email::get_welcome_email_template};
+
+#[tracing::instrument(name = "Show the welcome email form", skip(pool, flash_messages))]
+pub async fn welcome_email_form(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let template = get_welcome_email_template(pool.get_ref())
+        .await
+        .map_err(e500)?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let checked = |flag: bool| if flag { " checked" } else { "" };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Welcome email</title>
+</head>
+<body>
+    {msg_html}
+    <p>Sent to new subscribers once they have confirmed their subscription.</p>
+    <form action="/admin/welcome_email" method="post">
+        <label>
+            <input type="checkbox" name="enabled"{enabled}>
+            Send a welcome email
+        </label>
+        <br>
+        <label>Subject
+            <input
+                type="text"
+                placeholder="Enter Subject"
+                name="subject"
+                value="{subject}"
+            >
+        </label>
+        <br>
+        <label>HTML Content
+            <textarea name="html_content">{html_content}</textarea>
+        </label>
+        <br>
+        <label>Text Content
+            <textarea name="text_content">{text_content}</textarea>
+        </label>
+        <br>
+        <label>
+            <input type="checkbox" name="include_latest_issue"{include_latest_issue}>
+            Append the latest issue of the public archive
+        </label>
+        <br>
+        <button type="submit">Save</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+            enabled = checked(template.enabled),
+            subject = encode_attribute(&template.subject),
+            html_content = encode_minimal(&template.html_content),
+            text_content = encode_minimal(&template.text_content),
+            include_latest_issue = checked(template.include_latest_issue),
+        )))
+}
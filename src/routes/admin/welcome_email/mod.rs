@@ -0,0 +1,4 @@
+mod get;
+pub use get::welcome_email_form;
+mod post;
+pub use post::update_welcome_email;
@@ -0,0 +1,74 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::{
+    domain::IssueTemplate,
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    /// Set by the checkbox.
+    enabled: Option<String>,
+    subject: String,
+    html_content: String,
+    text_content: String,
+    /// Set by the checkbox.
+    include_latest_issue: Option<String>,
+}
+
+#[tracing::instrument(name = "Update the welcome email", skip(form, pool))]
+pub async fn update_welcome_email(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        enabled,
+        subject,
+        html_content,
+        text_content,
+        include_latest_issue,
+    } = form.0;
+    if let Err(e) = validate(&subject, &html_content, &text_content) {
+        FlashMessage::error(htmlescape::encode_minimal(&e)).send();
+        return Ok(see_other("/admin/welcome_email"));
+    }
+    sqlx::query!(
+        r#"
+        UPDATE welcome_email_template
+        SET
+            enabled = $1,
+            subject = $2,
+            html_content = $3,
+            text_content = $4,
+            include_latest_issue = $5,
+            updated_at = now()
+        "#,
+        enabled.is_some(),
+        subject.trim(),
+        html_content,
+        text_content,
+        include_latest_issue.is_some()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to update the welcome email.")
+    .map_err(e500)?;
+    FlashMessage::info("The welcome email has been saved.").send();
+
+    Ok(see_other("/admin/welcome_email"))
+}
+
+fn validate(subject: &str, html_content: &str, text_content: &str) -> Result<(), String> {
+    if subject.trim().is_empty() {
+        return Err("The subject cannot be empty.".to_string());
+    }
+    IssueTemplate::parse(html_content)
+        .map_err(|e| format!("The HTML content is not a valid template. {e}"))?;
+    IssueTemplate::parse(text_content)
+        .map_err(|e| format!("The text content is not a valid template. {e}"))?;
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::render_for_archive;
+use crate::{
+    domain::{DeliveryStatus, IssueStatus},
+    startup::ApplicationBaseUrl,
+    utils::{e404, e500},
+};
+
+struct ArchivedIssue {
+    title: String,
+    html_content: String,
+    published_at: Option<DateTime<Utc>>,
+}
+
+/// The "view online" version of an issue.
+#[tracing::instrument(name = "Show an archived newsletter issue", skip(pool, base_url))]
+pub async fn issue(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let issue = get_archived_issue(&pool, newsletter_issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("There is no archived issue with the provided id."))?;
+    let content = render_for_archive(&issue.html_content, &base_url.0, newsletter_issue_id);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+<body>
+    <h1>{title}</h1>
+    <p>{published_at}</p>
+    <article>
+{content}
+    </article>
+    <p><a href="/issues">&lt;- All issues</a></p>
+</body>
+</html>"#,
+            title = encode_minimal(&issue.title),
+            published_at = issue
+                .published_at
+                .map(|t| t.format("%B %-d, %Y").to_string())
+                .unwrap_or_default(),
+        )))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_archived_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<ArchivedIssue>, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        ArchivedIssue,
+        r#"
+        SELECT title, html_content, published_at
+        FROM newsletter_issues
+        WHERE
+            newsletter_issue_id = $1 AND
+            (
+                status IN ($2, $3) OR
+                -- Recipients of a partially delivered issue keep their "view online" link
+                (
+                    status = $4 AND
+                    EXISTS (
+                        SELECT 1
+                        FROM issue_deliveries
+                        WHERE
+                            newsletter_issue_id = $1 AND
+                            status = $5
+                    )
+                )
+            ) AND
+            NOT hidden_from_archive
+        "#,
+        newsletter_issue_id,
+        IssueStatus::Published.as_str(),
+        IssueStatus::Paused.as_str(),
+        IssueStatus::Cancelled.as_str(),
+        DeliveryStatus::Sent.as_str()
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve an archived newsletter issue.")?;
+
+    Ok(issue)
+}
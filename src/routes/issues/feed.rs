@@ -0,0 +1,235 @@
+use actix_web::{
+    http::header::{self, CacheControl, CacheDirective, ContentType, EntityTag},
+    web, HttpRequest, HttpResponse,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::render_for_archive;
+use crate::{
+    domain::IssueStatus, issue_delivery_worker::view_online_url, startup::ApplicationBaseUrl,
+    utils::e500,
+};
+
+const FEED_TITLE: &str = "Newsletter archive";
+const FEED_LENGTH: i64 = 20;
+/// Feed readers poll often: let them and any proxy in between reuse a feed for a while.
+const FEED_MAX_AGE_SECONDS: u32 = 300;
+
+struct FeedEntry {
+    newsletter_issue_id: Uuid,
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Serve the RSS feed", skip_all)]
+pub async fn rss_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let entries = get_feed_entries(&pool).await.map_err(e500)?;
+    let body = rss(&entries, &base_url.0);
+
+    Ok(feed_response(&request, "application/rss+xml", body))
+}
+
+#[tracing::instrument(name = "Serve the Atom feed", skip_all)]
+pub async fn atom_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let entries = get_feed_entries(&pool).await.map_err(e500)?;
+    let body = atom(&entries, &base_url.0);
+
+    Ok(feed_response(&request, "application/atom+xml", body))
+}
+
+/// Answer conditional requests with `304 Not Modified` when the feed has not changed.
+fn feed_response(request: &HttpRequest, media_type: &str, body: String) -> HttpResponse {
+    let etag = EntityTag::new_strong(format!("{:x}", Sha256::digest(body.as_bytes())));
+    let cache_control = CacheControl(vec![
+        CacheDirective::Public,
+        CacheDirective::MaxAge(FEED_MAX_AGE_SECONDS),
+    ]);
+    let unchanged = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|h| {
+            h.split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag == etag.to_string())
+        });
+    if unchanged {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .insert_header(cache_control)
+            .finish();
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType(
+            format!("{media_type}; charset=utf-8").parse().unwrap(),
+        ))
+        .insert_header(header::ETag(etag))
+        .insert_header(cache_control)
+        .body(body)
+}
+
+fn rss(entries: &[FeedEntry], base_url: &str) -> String {
+    let mut items = String::new();
+    for entry in entries {
+        let link = view_online_url(base_url, entry.newsletter_issue_id);
+        let content = render_for_archive(&entry.html_content, base_url, entry.newsletter_issue_id);
+        write!(
+            items,
+            r#"
+    <item>
+      <title>{title}</title>
+      <link>{link}</link>
+      <guid isPermaLink="true">{link}</guid>
+      <pubDate>{published_at}</pubDate>
+      <description>{content}</description>
+    </item>"#,
+            title = escape_xml(&entry.title),
+            link = escape_xml(&link),
+            published_at = entry.published_at.to_rfc2822(),
+            content = escape_xml(&content),
+        )
+        .unwrap();
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+  <channel>
+    <title>{FEED_TITLE}</title>
+    <link>{base_url}/issues</link>
+    <description>Every issue of our newsletter.</description>
+    <atom:link href="{base_url}/feed.rss" rel="self" type="application/rss+xml"/>{items}
+  </channel>
+</rss>
+"#,
+        base_url = escape_xml(base_url),
+    )
+}
+
+fn atom(entries: &[FeedEntry], base_url: &str) -> String {
+    let mut items = String::new();
+    for entry in entries {
+        let link = view_online_url(base_url, entry.newsletter_issue_id);
+        let content = render_for_archive(&entry.html_content, base_url, entry.newsletter_issue_id);
+        write!(
+            items,
+            r#"
+  <entry>
+    <id>urn:uuid:{id}</id>
+    <title>{title}</title>
+    <link href="{link}"/>
+    <published>{published_at}</published>
+    <updated>{published_at}</updated>
+    <content type="html">{content}</content>
+  </entry>"#,
+            id = entry.newsletter_issue_id,
+            title = escape_xml(&entry.title),
+            link = escape_xml(&link),
+            published_at = entry.published_at.to_rfc3339(),
+            content = escape_xml(&content),
+        )
+        .unwrap();
+    }
+    // Entries come newest first; an empty feed was last updated at the epoch, which keeps it stable
+    let updated = entries
+        .first()
+        .map(|e| e.published_at)
+        .unwrap_or_default()
+        .to_rfc3339();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>{base_url}/issues</id>
+  <title>{FEED_TITLE}</title>
+  <updated>{updated}</updated>
+  <author><name>{FEED_TITLE}</name></author>
+  <link href="{base_url}/issues"/>
+  <link href="{base_url}/feed.atom" rel="self"/>{items}
+</feed>
+"#,
+        base_url = escape_xml(base_url),
+    )
+}
+
+/// Escape text for use in XML content or attribute values.
+///
+/// Characters XML 1.0 does not allow at all, even escaped, are dropped.
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_feed_entries(pool: &PgPool) -> Result<Vec<FeedEntry>, anyhow::Error> {
+    let entries = sqlx::query_as!(
+        FeedEntry,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            html_content,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE
+            status IN ($1, $2) AND
+            NOT hidden_from_archive
+        ORDER BY published_at DESC, newsletter_issue_id
+        LIMIT $3
+        "#,
+        IssueStatus::Published.as_str(),
+        IssueStatus::Paused.as_str(),
+        FEED_LENGTH
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve newsletter issues for the feeds.")?;
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape_xml;
+
+    #[test]
+    fn markup_is_escaped() {
+        assert_eq!(
+            escape_xml(r#"<p class="x">Tom & Jerry's</p>"#),
+            "&lt;p class=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/p&gt;"
+        );
+    }
+
+    #[test]
+    fn characters_xml_does_not_allow_are_dropped() {
+        assert_eq!(escape_xml("a\u{0}b\u{1b}c\td\ne"), "abc\td\ne");
+    }
+}
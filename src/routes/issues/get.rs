@@ -0,0 +1,121 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::{domain::IssueStatus, utils::e500};
+
+const ISSUES_PER_PAGE: i64 = 10;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Pagination {
+    /// 1-based, newest issues first.
+    page: Option<i64>,
+}
+
+struct ArchivedIssue {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Show the newsletter archive", skip(pool))]
+pub async fn issues(
+    pagination: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = pagination.page.unwrap_or(1).max(1);
+    let mut issues = get_archived_issues(&pool, page).await.map_err(e500)?;
+    // One extra issue is fetched to know whether there is a next page
+    let has_next_page = issues.len() as i64 > ISSUES_PER_PAGE;
+    issues.truncate(ISSUES_PER_PAGE as usize);
+
+    let mut issues_html = String::new();
+    for issue in issues {
+        writeln!(
+            issues_html,
+            r#"        <li><a href="/issues/{}">{}</a> {}</li>"#,
+            issue.newsletter_issue_id,
+            encode_minimal(&issue.title),
+            issue
+                .published_at
+                .map(|t| t.format("%B %-d, %Y").to_string())
+                .unwrap_or_default(),
+        )
+        .unwrap();
+    }
+    if issues_html.is_empty() {
+        issues_html.push_str("        <li>No issues yet.</li>");
+    }
+    let mut pages_html = String::new();
+    if page > 1 {
+        write!(
+            pages_html,
+            r#"<a href="/issues?page={}">Newer issues</a> "#,
+            page - 1
+        )
+        .unwrap();
+    }
+    if has_next_page {
+        write!(
+            pages_html,
+            r#"<a href="/issues?page={}">Older issues</a>"#,
+            page + 1
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Newsletter archive</title>
+    <link rel="alternate" type="application/rss+xml" title="RSS" href="/feed.rss">
+    <link rel="alternate" type="application/atom+xml" title="Atom" href="/feed.atom">
+</head>
+<body>
+    <h1>Newsletter archive</h1>
+    <ul>
+{issues_html}
+    </ul>
+    <p>{pages_html}</p>
+    <p><a href="/">&lt;- Home</a></p>
+</body>
+</html>"#
+        )))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_archived_issues(
+    pool: &PgPool,
+    page: i64,
+) -> Result<Vec<ArchivedIssue>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        ArchivedIssue,
+        r#"
+        SELECT newsletter_issue_id, title, published_at
+        FROM newsletter_issues
+        WHERE
+            status IN ($1, $2) AND
+            NOT hidden_from_archive
+        ORDER BY published_at DESC, newsletter_issue_id
+        LIMIT $3
+        OFFSET $4
+        "#,
+        IssueStatus::Published.as_str(),
+        IssueStatus::Paused.as_str(),
+        ISSUES_PER_PAGE + 1,
+        (page - 1).saturating_mul(ISSUES_PER_PAGE)
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve archived newsletter issues.")?;
+
+    Ok(issues)
+}
@@ -0,0 +1,31 @@
+mod detail;
+pub use detail::issue;
+mod feed;
+pub use feed::{atom_feed, rss_feed};
+mod get;
+pub use get::issues;
+
+use uuid::Uuid;
+
+use crate::{
+    domain::{IssueTemplate, TemplateContext},
+    issue_delivery_worker::view_online_url,
+};
+
+/// Render the HTML body of an issue for the archive and the feeds.
+///
+/// Their readers are anonymous: placeholders get neutral values.
+fn render_for_archive(html_content: &str, base_url: &str, newsletter_issue_id: Uuid) -> String {
+    let home_url = format!("{base_url}/");
+    let view_online_url = view_online_url(base_url, newsletter_issue_id);
+    let context = TemplateContext {
+        subscriber_name: "reader",
+        subscriber_email: "",
+        unsubscribe_url: &home_url,
+        view_online_url: &view_online_url,
+    };
+
+    IssueTemplate::parse(html_content)
+        .unwrap_or_else(|_| IssueTemplate::verbatim(html_content))
+        .render_html(&context)
+}
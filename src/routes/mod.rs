@@ -2,14 +2,23 @@ mod health_check;
 pub use health_check::health_check;
 mod home;
 pub use home::home;
+mod issues;
+pub use issues::{atom_feed, issue, issues, rss_feed};
 mod login;
 pub use login::{login, login_form};
 mod subscriptions;
 pub use subscriptions::subscribe;
 mod subscriptions_confirm;
 pub use subscriptions_confirm::confirm;
+mod subscriptions_unsubscribe;
+pub use subscriptions_unsubscribe::{unsubscribe, unsubscribe_form};
 mod admin;
 pub use admin::{
-    admin_dashboard, change_password, change_password_form, logout, publish_newsletter,
-    publish_newsletter_form,
+    account_email_form, admin_dashboard, cancel_newsletter_issue, cancel_subscription,
+    change_account_email, change_password, change_password_form, confirm_subscriber, create_draft,
+    dead_letters, delete_subscriber, draft_form, drafts, logout, newsletter_issue_detail,
+    newsletter_issues, pause_newsletter_issue, preview_draft, publish_draft, publish_newsletter,
+    publish_newsletter_form, requeue_dead_letter, requeue_email_dead_letter,
+    resume_newsletter_issue, send_test_email, subscriber_detail, subscribers, update_draft,
+    update_welcome_email, welcome_email_form,
 };
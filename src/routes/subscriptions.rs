@@ -5,12 +5,13 @@ use reqwest::StatusCode;
 use sqlx::{PgPool, Postgres, Transaction};
 
 use crate::{
-    domain::{NewSubscriber, SubscriberEmail, SubscriberName},
-    email_client::EmailClient,
-    startup::ApplicationBaseUrl,
+    domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionStatus},
+    email_client::Email,
+    email_outbox::enqueue_email,
+    startup::{ApplicationBaseUrl, ConfirmationResendCooldown, ConfirmationTokenTtl},
     utils::error_chain_fmt,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 #[derive(serde::Deserialize)]
@@ -32,7 +33,7 @@ impl TryFrom<FormData> for NewSubscriber {
 
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool, email_client, base_url),
+    skip(form, pool, base_url, token_ttl, resend_cooldown),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
@@ -41,33 +42,73 @@ impl TryFrom<FormData> for NewSubscriber {
 pub async fn subscribe(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
-    email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseUrl>,
+    token_ttl: web::Data<ConfirmationTokenTtl>,
+    resend_cooldown: web::Data<ConfirmationResendCooldown>,
 ) -> Result<HttpResponse, SubscribeError> {
     let new_subscriber = form.0.try_into().map_err(SubscribeError::ValidationError)?;
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool.")?;
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
-        .await
-        .context("Failed to insert a new subscriber in the database.")?;
-    let subscription_token = generate_subscription_token();
-    store_token(&mut transaction, subscriber_id, &subscription_token)
-        .await
-        .context("Failed to store the confirmation token for a new subscriber.")?;
-    transaction
-        .commit()
+    let (subscriber_id, status, confirmation_requested_at) =
+        lock_or_insert_subscriber(&mut transaction, &new_subscriber)
+            .await
+            .context("Failed to insert a new subscriber in the database.")?;
+    let subscription_token = match status {
+        // Nothing to do, and nothing to tell: whether an address is subscribed is not public
+        SubscriptionStatus::Confirmed => {
+            tracing::info!(%subscriber_id, "The subscriber has already confirmed.");
+            return Ok(HttpResponse::Ok().finish());
+        }
+        // They may have lost the confirmation email or let it expire: send it again
+        SubscriptionStatus::PendingConfirmation => {
+            // Repeated sign-ups must not turn us into a mail bomb for someone else's inbox
+            if confirmation_requested_at.is_some_and(|at| at > Utc::now() - resend_cooldown.0) {
+                tracing::info!(%subscriber_id, "A confirmation email was requested recently.");
+                return Ok(HttpResponse::Ok().finish());
+            }
+            match get_valid_token(&mut transaction, subscriber_id, token_ttl.0)
+                .await
+                .context("Failed to retrieve the confirmation token of a pending subscriber.")?
+            {
+                Some(subscription_token) => subscription_token,
+                None => {
+                    let subscription_token = generate_subscription_token();
+                    store_token(&mut transaction, subscriber_id, &subscription_token)
+                        .await
+                        .context("Failed to store the confirmation token for a new subscriber.")?;
+                    subscription_token
+                }
+            }
+        }
+        // Coming back requires a fresh opt-in, with a token that was never sent before
+        SubscriptionStatus::Unsubscribed => {
+            resubscribe(&mut transaction, subscriber_id, &new_subscriber)
+                .await
+                .context("Failed to re-activate an unsubscribed subscriber.")?;
+            let subscription_token = generate_subscription_token();
+            store_token(&mut transaction, subscriber_id, &subscription_token)
+                .await
+                .context("Failed to store the confirmation token for a returning subscriber.")?;
+            subscription_token
+        }
+    };
+    record_confirmation_request(&mut transaction, subscriber_id)
         .await
-        .context("Failed to commit SQL transaction to store a new subscriber.")?;
-    send_confirmation_email(
-        &email_client,
-        new_subscriber,
+        .context("Failed to record when the confirmation email was requested.")?;
+    enqueue_confirmation_email(
+        &mut transaction,
+        &new_subscriber,
         &base_url.0,
         &subscription_token,
     )
     .await
-    .context("Failed to send a confirmation email")?;
+    .context("Failed to enqueue a confirmation email")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a new subscriber.")?;
 
     Ok(HttpResponse::Ok().finish())
 }
@@ -95,30 +136,133 @@ impl ResponseError for SubscribeError {
     }
 }
 
-/// Insert subscriber to DB and return the unique identifier.
+/// Lock the subscription of `new_subscriber`, creating a pending one if there is none yet.
+/// Also returns when a confirmation email was last enqueued for it, if ever.
 #[tracing::instrument(
     name = "Saving new subscriber details in the database",
     skip(transaction, new_subscriber)
 )]
-async fn insert_subscriber(
+async fn lock_or_insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
-) -> Result<Uuid, sqlx::Error> {
+) -> Result<(Uuid, SubscriptionStatus, Option<DateTime<Utc>>), anyhow::Error> {
     let subscriber_id = Uuid::new_v4();
-    sqlx::query!(
+    // Concurrent sign-ups with the same address wait here for each other
+    let inserted = sqlx::query!(
         r#"
         INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (email) DO NOTHING
         "#,
         subscriber_id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
         Utc::now(),
+        SubscriptionStatus::PendingConfirmation.as_str()
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected()
+        > 0;
+    if inserted {
+        return Ok((subscriber_id, SubscriptionStatus::PendingConfirmation, None));
+    }
+    let existing = sqlx::query!(
+        r#"
+        SELECT id, status, confirmation_requested_at
+        FROM subscriptions
+        WHERE email = $1
+        FOR UPDATE
+        "#,
+        new_subscriber.email.as_ref()
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    let status = SubscriptionStatus::parse(&existing.status).map_err(anyhow::Error::msg)?;
+
+    Ok((existing.id, status, existing.confirmation_requested_at))
+}
+
+/// Put an unsubscribed subscriber back to `pending_confirmation`, revoking the tokens they
+/// were sent before unsubscribing.
+#[tracing::instrument(skip(transaction, new_subscriber))]
+async fn resubscribe(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    new_subscriber: &NewSubscriber,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET
+            name = $2,
+            status = $3,
+            unsubscribed_at = NULL
+        WHERE id = $1
+        "#,
+        subscriber_id,
+        new_subscriber.name.as_ref(),
+        SubscriptionStatus::PendingConfirmation.as_str()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens
+        WHERE subscriber_id = $1
+        "#,
+        subscriber_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn get_valid_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    token_ttl: chrono::Duration,
+) -> Result<Option<String>, sqlx::Error> {
+    let subscription_token = sqlx::query!(
+        r#"
+        SELECT subscription_token
+        FROM subscription_tokens
+        WHERE
+            subscriber_id = $1 AND
+            used_at IS NULL AND
+            created_at > $2
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        subscriber_id,
+        Utc::now() - token_ttl
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?
+    .map(|r| r.subscription_token);
+
+    Ok(subscription_token)
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn record_confirmation_request(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET confirmation_requested_at = now()
+        WHERE id = $1
+        "#,
+        subscriber_id
     )
     .execute(transaction.as_mut())
     .await?;
 
-    Ok(subscriber_id)
+    Ok(())
 }
 
 fn generate_subscription_token() -> String {
@@ -148,15 +292,15 @@ async fn store_token(
 }
 
 #[tracing::instrument(
-    name = "Send a confirmation email to a new subscriber",
-    skip(email_client, new_subscriber, base_url, subscription_token)
+    name = "Enqueue a confirmation email for a new subscriber",
+    skip(transaction, new_subscriber, base_url, subscription_token)
 )]
-async fn send_confirmation_email(
-    email_client: &EmailClient,
-    new_subscriber: NewSubscriber,
+async fn enqueue_confirmation_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    new_subscriber: &NewSubscriber,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), sqlx::Error> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token,
@@ -170,9 +314,12 @@ async fn send_confirmation_email(
         "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
         confirmation_link
     );
-    email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
-        .await?;
-
-    Ok(())
+    let email = Email {
+        recipient: &new_subscriber.email,
+        subject: "Welcome!",
+        html_content: &html_body,
+        text_content: &plain_body,
+        headers: &[],
+    };
+    enqueue_email(transaction, &email).await
 }
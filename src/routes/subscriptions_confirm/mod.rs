@@ -0,0 +1,215 @@
+use actix_web::{http::header::ContentType, web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    domain::SubscriptionStatus, startup::ConfirmationTokenTtl, utils::error_chain_fmt,
+    welcome_email::enqueue_welcome_email,
+};
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    subscription_token: String,
+}
+
+#[tracing::instrument(
+    name = "Confirm a pending subscriber."
+    skip(pool, parameters, token_ttl),
+)]
+pub async fn confirm(
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+    token_ttl: web::Data<ConfirmationTokenTtl>,
+) -> Result<HttpResponse, ConfirmationError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let token = get_subscription_token(&mut transaction, &parameters.subscription_token)
+        .await
+        .context("Failed to get subscriber id from token")?
+        .ok_or(ConfirmationError::UnknownToken)?;
+    // Following the link twice, or after being confirmed by an admin, is not an error
+    let page = if token.status == SubscriptionStatus::Confirmed.as_str() {
+        include_str!("already_confirmed.html")
+    } else if token.used_at.is_some() {
+        return Err(ConfirmationError::UsedToken);
+    } else if token.status != SubscriptionStatus::PendingConfirmation.as_str() {
+        // Unsubscribing revokes tokens: only links issued before that was the case get here
+        return Err(ConfirmationError::CancelledSubscription);
+    } else if token.created_at + token_ttl.0 <= Utc::now() {
+        return Err(ConfirmationError::ExpiredToken {
+            email: token.email,
+            name: token.name,
+        });
+    } else {
+        let confirmed = mark_as_confirmed(transaction.as_mut(), token.subscriber_id)
+            .await
+            .context("Failed to update subscriber status to `confirmed`")?;
+        if !confirmed {
+            return Err(ConfirmationError::CancelledSubscription);
+        }
+        enqueue_welcome_email(&mut transaction, token.subscriber_id)
+            .await
+            .context("Failed to enqueue a welcome email")?;
+        include_str!("confirmed.html")
+    };
+    mark_token_as_used(&mut transaction, &parameters.subscription_token)
+        .await
+        .context("Failed to mark the subscription token as used")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to confirm a subscriber.")?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page))
+}
+
+#[derive(thiserror::Error)]
+pub enum ConfirmationError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+    #[error("There is no subscriber associated with the provided token.")]
+    UnknownToken,
+    #[error("The provided token has already been used.")]
+    UsedToken,
+    #[error("The subscription has been cancelled.")]
+    CancelledSubscription,
+    #[error("The provided token has expired.")]
+    ExpiredToken { email: String, name: String },
+}
+
+impl std::fmt::Debug for ConfirmationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ConfirmationError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ConfirmationError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ConfirmationError::UnknownToken
+            | ConfirmationError::UsedToken
+            | ConfirmationError::CancelledSubscription => StatusCode::BAD_REQUEST,
+            ConfirmationError::ExpiredToken { .. } => StatusCode::GONE,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let ConfirmationError::ExpiredToken { email, name } = self else {
+            return HttpResponse::build(self.status_code()).body(self.to_string());
+        };
+        let email_text = htmlescape::encode_minimal(email);
+        let email = htmlescape::encode_attribute(email);
+        let name = htmlescape::encode_attribute(name);
+
+        // Submitting the sign-up form again sends a fresh confirmation link
+        HttpResponse::build(self.status_code())
+            .content_type(ContentType::html())
+            .body(format!(
+                r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Confirmation link expired</title>
+</head>
+<body>
+    <p>This confirmation link has expired.</p>
+    <p>We can send a new one to {email_text}.</p>
+    <form action="/subscriptions" method="post">
+        <input type="hidden" name="email" value="{email}">
+        <input type="hidden" name="name" value="{name}">
+        <button type="submit">Resend the confirmation email</button>
+    </form>
+</body>
+</html>"#
+            ))
+    }
+}
+
+struct SubscriptionToken {
+    subscriber_id: Uuid,
+    created_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+    email: String,
+    name: String,
+    status: String,
+}
+
+/// Look the token up, locking it until the transaction ends so that it is only used once.
+#[tracing::instrument(
+    "Get subscriber_id from subscription_token.",
+    skip(transaction, subscription_token)
+)]
+async fn get_subscription_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+) -> Result<Option<SubscriptionToken>, sqlx::Error> {
+    sqlx::query_as!(
+        SubscriptionToken,
+        r#"
+        SELECT t.subscriber_id, t.created_at, t.used_at, s.email, s.name, s.status
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE t.subscription_token = $1
+        FOR UPDATE OF t
+        "#,
+        subscription_token
+    )
+    .fetch_optional(transaction.as_mut())
+    .await
+}
+
+#[tracing::instrument(skip(transaction, subscription_token))]
+async fn mark_token_as_used(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscription_tokens
+        SET used_at = COALESCE(used_at, now())
+        WHERE subscription_token = $1
+        "#,
+        subscription_token
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(())
+}
+
+/// Confirm a subscriber pending confirmation, recording when it happened.
+///
+/// Returns whether they were pending confirmation.
+#[tracing::instrument(name = "Change subscriber status to confirmed", skip(executor))]
+pub(crate) async fn mark_as_confirmed(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET
+            status = $3,
+            confirmed_at = now()
+        WHERE
+            id = $1 AND
+            status = $2
+        "#,
+        subscriber_id,
+        SubscriptionStatus::PendingConfirmation.as_str(),
+        SubscriptionStatus::Confirmed.as_str()
+    )
+    .execute(executor)
+    .await?
+    .rows_affected();
+
+    Ok(n_updated_rows > 0)
+}
@@ -0,0 +1,32 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+
+use super::{Parameters, UnsubscribeError};
+use crate::{domain::UnsubscribeToken, startup::HmacSecret};
+
+#[tracing::instrument(name = "Show the unsubscribe form", skip(parameters, hmac_secret))]
+pub async fn unsubscribe_form(
+    parameters: web::Query<Parameters>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    UnsubscribeToken::verify(&parameters.unsubscribe_token, &hmac_secret.0)
+        .map_err(UnsubscribeError::InvalidToken)?;
+    let unsubscribe_token = urlencoding::encode(&parameters.unsubscribe_token);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribe</title>
+</head>
+<body>
+    <p>Do you want to stop receiving our newsletter?</p>
+    <form action="/subscriptions/unsubscribe?unsubscribe_token={unsubscribe_token}" method="post">
+        <button type="submit">Unsubscribe</button>
+    </form>
+</body>
+</html>"#
+        )))
+}
@@ -0,0 +1,42 @@
+use actix_web::ResponseError;
+use reqwest::StatusCode;
+
+use crate::utils::error_chain_fmt;
+
+mod get;
+pub use get::unsubscribe_form;
+mod post;
+pub use post::unsubscribe;
+pub(crate) use post::{drop_pending_deliveries, unsubscribe_subscriber};
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    unsubscribe_token: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum UnsubscribeError {
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+    #[error("The unsubscribe token is invalid.")]
+    InvalidToken(#[source] anyhow::Error),
+    #[error("There is no subscriber associated with the provided token.")]
+    UnknownSubscriber,
+}
+
+impl std::fmt::Debug for UnsubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for UnsubscribeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            UnsubscribeError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            UnsubscribeError::InvalidToken(_) | UnsubscribeError::UnknownSubscriber => {
+                StatusCode::BAD_REQUEST
+            }
+        }
+    }
+}
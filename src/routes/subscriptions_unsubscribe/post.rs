@@ -0,0 +1,115 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{Parameters, UnsubscribeError};
+use crate::{
+    domain::{DeliveryStatus, SubscriptionStatus, UnsubscribeToken},
+    startup::HmacSecret,
+};
+
+#[tracing::instrument(
+    name = "Unsubscribe a subscriber",
+    skip(pool, parameters, hmac_secret),
+    fields(subscriber_id = tracing::field::Empty)
+)]
+pub async fn unsubscribe(
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    let subscriber_id = UnsubscribeToken::verify(&parameters.unsubscribe_token, &hmac_secret.0)
+        .map_err(UnsubscribeError::InvalidToken)?;
+    tracing::Span::current().record("subscriber_id", tracing::field::display(&subscriber_id));
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let email = unsubscribe_subscriber(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to update subscriber status to `unsubscribed`")?
+        .ok_or(UnsubscribeError::UnknownSubscriber)?;
+    drop_pending_deliveries(&mut transaction, &email)
+        .await
+        .context("Failed to remove pending deliveries for an unsubscribed subscriber")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to unsubscribe a subscriber.")?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(include_str!("unsubscribed.html")))
+}
+
+/// Mark the subscriber as unsubscribed, revoking their confirmation links, and return their
+/// email address.
+#[tracing::instrument(name = "Change subscriber status to unsubscribed", skip(transaction))]
+pub(crate) async fn unsubscribe_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let email = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET
+            status = $2,
+            unsubscribed_at = COALESCE(unsubscribed_at, now())
+        WHERE id = $1
+        RETURNING email
+        "#,
+        subscriber_id,
+        SubscriptionStatus::Unsubscribed.as_str()
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?
+    .map(|r| r.email);
+    // A confirmation link still lying in their inbox must not bring them back
+    sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens
+        WHERE subscriber_id = $1
+        "#,
+        subscriber_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(email)
+}
+
+/// Make sure no more issues are delivered to `email`.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn drop_pending_deliveries(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE issue_deliveries
+        SET
+            status = $2,
+            updated_at = now()
+        WHERE
+            subscriber_email = $1 AND
+            status = $3
+        "#,
+        email,
+        DeliveryStatus::SkippedUnsubscribed.as_str(),
+        DeliveryStatus::Queued.as_str()
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE subscriber_email = $1
+        "#,
+        email
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(())
+}
@@ -6,15 +6,23 @@ use actix_web_flash_messages::{storage::CookieMessageStore, FlashMessagesFramewo
 use actix_web_lab::middleware::from_fn;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{postgres::PgPoolOptions, PgPool};
+use tokio_util::sync::CancellationToken;
 use tracing_actix_web::TracingLogger;
 
 use crate::{
     authentication::reject_anonymous_users,
-    configuration::{DatabaseSettings, Settings},
+    configuration::{ApplicationSettings, DatabaseSettings, Settings},
     email_client::EmailClient,
     routes::{
-        admin_dashboard, change_password, change_password_form, confirm, health_check, home, login,
-        login_form, logout, publish_newsletter, publish_newsletter_form, subscribe,
+        account_email_form, admin_dashboard, atom_feed, cancel_newsletter_issue,
+        cancel_subscription, change_account_email, change_password, change_password_form, confirm,
+        confirm_subscriber, create_draft, dead_letters, delete_subscriber, draft_form, drafts,
+        health_check, home, issue, issues, login, login_form, logout, newsletter_issue_detail,
+        newsletter_issues, pause_newsletter_issue, preview_draft, publish_draft,
+        publish_newsletter, publish_newsletter_form, requeue_dead_letter,
+        requeue_email_dead_letter, resume_newsletter_issue, rss_feed, send_test_email, subscribe,
+        subscriber_detail, subscribers, unsubscribe, unsubscribe_form, update_draft,
+        update_welcome_email, welcome_email_form,
     },
 };
 
@@ -37,9 +45,10 @@ impl Application {
             listener,
             connection_pool,
             email_client,
-            configuration.application.base_url,
-            configuration.application.hmac_secret,
+            configuration.application,
             configuration.redis_uri,
+            configuration.subscriptions.confirmation_token_ttl(),
+            configuration.subscriptions.confirmation_resend_cooldown(),
         )
         .await?;
 
@@ -50,31 +59,60 @@ impl Application {
         self.port
     }
 
-    pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
+    /// Serve requests until `shutdown` is cancelled, then stop accepting connections
+    /// and give in-flight requests the drain timeout to complete.
+    pub async fn run_until_stopped(
+        self,
+        shutdown: CancellationToken,
+    ) -> Result<(), std::io::Error> {
+        let server_handle = self.server.handle();
+        tokio::spawn(async move {
+            shutdown.cancelled().await;
+            server_handle.stop(true).await;
+        });
+
         self.server.await
     }
 }
 
 pub fn get_connection_pool(configuration: &DatabaseSettings) -> PgPool {
-    PgPoolOptions::new()
-        .acquire_timeout(std::time::Duration::from_secs(2))
+    pool_options().connect_lazy_with(configuration.with_db())
+}
+
+/// A pool holding at most `max_connections`, for processes whose connection needs are known
+/// upfront.
+pub fn get_connection_pool_with_size(
+    configuration: &DatabaseSettings,
+    max_connections: u32,
+) -> PgPool {
+    pool_options()
+        .max_connections(max_connections)
         .connect_lazy_with(configuration.with_db())
 }
 
+fn pool_options() -> PgPoolOptions {
+    PgPoolOptions::new().acquire_timeout(std::time::Duration::from_secs(2))
+}
+
 pub struct ApplicationBaseUrl(pub String);
 
 async fn run(
     listener: TcpListener,
     db_pool: PgPool,
     email_client: EmailClient,
-    base_url: String,
-    hmac_secret: Secret<String>,
+    application: ApplicationSettings,
     redis_uri: Secret<String>,
+    confirmation_token_ttl: chrono::Duration,
+    confirmation_resend_cooldown: chrono::Duration,
 ) -> Result<Server, anyhow::Error> {
     let db_pool = web::Data::new(db_pool);
     let email_client = web::Data::new(email_client);
-    let base_url = web::Data::new(ApplicationBaseUrl(base_url));
-    let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
+    let base_url = web::Data::new(ApplicationBaseUrl(application.base_url));
+    let secret_key = Key::from(application.hmac_secret.expose_secret().as_bytes());
+    let hmac_secret = web::Data::new(HmacSecret(application.hmac_secret));
+    let confirmation_token_ttl = web::Data::new(ConfirmationTokenTtl(confirmation_token_ttl));
+    let confirmation_resend_cooldown =
+        web::Data::new(ConfirmationResendCooldown(confirmation_resend_cooldown));
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
     let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
@@ -92,21 +130,99 @@ async fn run(
             .route("/health_check", web::get().to(health_check))
             .route("/subscriptions", web::post().to(subscribe))
             .route("/subscriptions/confirm", web::get().to(confirm))
+            .route(
+                "/subscriptions/unsubscribe",
+                web::get().to(unsubscribe_form),
+            )
+            .route("/subscriptions/unsubscribe", web::post().to(unsubscribe))
+            .route("/issues", web::get().to(issues))
+            .route("/issues/{newsletter_issue_id}", web::get().to(issue))
+            .route("/feed.rss", web::get().to(rss_feed))
+            .route("/feed.atom", web::get().to(atom_feed))
             .service(
                 web::scope("/admin")
                     .wrap(from_fn(reject_anonymous_users))
                     .route("/dashboard", web::get().to(admin_dashboard))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
+                    .route("/email", web::get().to(account_email_form))
+                    .route("/email", web::post().to(change_account_email))
                     .route("/newsletters", web::get().to(publish_newsletter_form))
                     .route("/newsletters", web::post().to(publish_newsletter))
+                    .route(
+                        "/newsletters/{newsletter_issue_id}",
+                        web::get().to(newsletter_issue_detail),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/cancel",
+                        web::post().to(cancel_newsletter_issue),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/pause",
+                        web::post().to(pause_newsletter_issue),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/resume",
+                        web::post().to(resume_newsletter_issue),
+                    )
+                    .route("/issues", web::get().to(newsletter_issues))
+                    .route("/drafts", web::get().to(drafts))
+                    .route("/drafts", web::post().to(create_draft))
+                    .route("/drafts/{newsletter_issue_id}", web::get().to(draft_form))
+                    .route(
+                        "/drafts/{newsletter_issue_id}",
+                        web::post().to(update_draft),
+                    )
+                    .route(
+                        "/drafts/{newsletter_issue_id}/preview",
+                        web::get().to(preview_draft),
+                    )
+                    .route(
+                        "/drafts/{newsletter_issue_id}/test",
+                        web::post().to(send_test_email),
+                    )
+                    .route(
+                        "/drafts/{newsletter_issue_id}/publish",
+                        web::post().to(publish_draft),
+                    )
+                    .route("/subscribers", web::get().to(subscribers))
+                    .route(
+                        "/subscribers/{subscriber_id}",
+                        web::get().to(subscriber_detail),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/confirm",
+                        web::post().to(confirm_subscriber),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/unsubscribe",
+                        web::post().to(cancel_subscription),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/delete",
+                        web::post().to(delete_subscriber),
+                    )
+                    .route("/welcome_email", web::get().to(welcome_email_form))
+                    .route("/welcome_email", web::post().to(update_welcome_email))
+                    .route("/dead_letters", web::get().to(dead_letters))
+                    .route("/dead_letters", web::post().to(requeue_dead_letter))
+                    .route(
+                        "/dead_letters/emails",
+                        web::post().to(requeue_email_dead_letter),
+                    )
                     .route("/logout", web::post().to(logout)),
             )
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(hmac_secret.clone())
+            .app_data(confirmation_token_ttl.clone())
+            .app_data(confirmation_resend_cooldown.clone())
     })
     .listen(listener)?
+    // Shutdown is driven by the caller, who also has to drain the delivery worker
+    .disable_signals()
+    .shutdown_timeout(application.drain_timeout_seconds)
     .run();
 
     Ok(server)
@@ -114,3 +230,11 @@ async fn run(
 
 #[derive(Clone)]
 pub struct HmacSecret(pub Secret<String>);
+
+/// How long a confirmation link stays valid after it was sent.
+#[derive(Clone, Copy)]
+pub struct ConfirmationTokenTtl(pub chrono::Duration);
+
+/// The minimum time between two confirmation emails to the same pending subscriber.
+#[derive(Clone, Copy)]
+pub struct ConfirmationResendCooldown(pub chrono::Duration);
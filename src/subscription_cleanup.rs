@@ -0,0 +1,86 @@
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+
+use crate::{configuration::SubscriptionSettings, domain::SubscriptionStatus};
+
+/// Delete pending subscriptions that were never confirmed within `retention`, with their tokens.
+///
+/// A subscription only counts as abandoned if no confirmation email was sent within `retention`
+/// either, so signing up again keeps it around.
+#[tracing::instrument(skip(pool), err)]
+pub async fn purge_abandoned_subscriptions(
+    pool: &PgPool,
+    retention: chrono::Duration,
+) -> Result<u64, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let cutoff = Utc::now() - retention;
+    let abandoned = sqlx::query!(
+        r#"
+        SELECT id
+        FROM subscriptions s
+        WHERE
+            status = $1 AND
+            subscribed_at < $2 AND
+            NOT EXISTS (
+                SELECT 1
+                FROM subscription_tokens t
+                WHERE
+                    t.subscriber_id = s.id AND
+                    t.created_at >= $2
+            )
+        FOR UPDATE
+        SKIP LOCKED
+        "#,
+        SubscriptionStatus::PendingConfirmation.as_str(),
+        cutoff
+    )
+    .fetch_all(transaction.as_mut())
+    .await?
+    .into_iter()
+    .map(|r| r.id)
+    .collect::<Vec<_>>();
+    sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens
+        WHERE subscriber_id = ANY($1)
+        "#,
+        &abandoned
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    let n_purged = sqlx::query!(
+        r#"
+        DELETE FROM subscriptions
+        WHERE id = ANY($1)
+        "#,
+        &abandoned
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    transaction.commit().await?;
+    if n_purged > 0 {
+        tracing::info!(n_purged, "Purged abandoned pending subscriptions");
+    }
+
+    Ok(n_purged)
+}
+
+/// Purge abandoned subscriptions every `cleanup_interval`, until `shutdown` is cancelled.
+pub async fn cleanup_loop(
+    pool: PgPool,
+    settings: SubscriptionSettings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    while !shutdown.is_cancelled() {
+        // Failures are already logged, and retried on the next iteration
+        let _ = purge_abandoned_subscriptions(&pool, settings.pending_retention()).await;
+        tokio::select! {
+            () = tokio::time::sleep(settings.cleanup_interval()) => {}
+            () = shutdown.cancelled() => {}
+        }
+    }
+
+    Ok(())
+}
@@ -33,3 +33,10 @@ where
 {
     actix_web::error::ErrorBadRequest(e)
 }
+
+pub fn e404<T>(e: T) -> actix_web::Error
+where
+    T: std::fmt::Debug + std::fmt::Display + 'static,
+{
+    actix_web::error::ErrorNotFound(e)
+}
@@ -0,0 +1,196 @@
+use secrecy::Secret;
+use sqlx::{PgExecutor, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        IssueStatus, IssueTemplate, SubscriberEmail, SubscriptionStatus, TemplateContext,
+        UnsubscribeToken,
+    },
+    email_client::EmailHeader,
+    issue_delivery_worker::{
+        list_unsubscribe_headers, notify_workers, unsubscribe_url, view_online_url,
+    },
+};
+
+/// The email sent to new subscribers once they have confirmed, as edited by admins.
+pub struct WelcomeEmailTemplate {
+    pub enabled: bool,
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+    /// Whether the latest issue in the public archive is appended to the welcome message.
+    pub include_latest_issue: bool,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_welcome_email_template(
+    executor: impl PgExecutor<'_>,
+) -> Result<WelcomeEmailTemplate, sqlx::Error> {
+    sqlx::query_as!(
+        WelcomeEmailTemplate,
+        r#"
+        SELECT enabled, subject, html_content, text_content, include_latest_issue
+        FROM welcome_email_template
+        "#
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Queue a welcome email for a subscriber who just confirmed, if welcome emails are enabled.
+///
+/// It is rendered by the worker right before each attempt to send it, and dropped if the
+/// subscriber has left in the meantime.
+#[tracing::instrument(skip(transaction))]
+pub async fn enqueue_welcome_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO email_outbox (
+            id,
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            header_names,
+            header_values,
+            welcome_subscriber_id
+        )
+        SELECT $1, s.email, '', '', '', '{}', '{}', s.id
+        FROM subscriptions s
+        CROSS JOIN welcome_email_template t
+        WHERE
+            s.id = $2 AND
+            t.enabled
+        "#,
+        Uuid::new_v4(),
+        subscriber_id
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    if n_inserted_rows > 0 {
+        notify_workers(transaction).await?;
+    }
+
+    Ok(())
+}
+
+/// A welcome email rendered for one subscriber.
+pub(crate) struct WelcomeEmail {
+    pub(crate) recipient: SubscriberEmail,
+    pub(crate) subject: String,
+    pub(crate) html_content: String,
+    pub(crate) text_content: String,
+    pub(crate) headers: Vec<EmailHeader>,
+}
+
+/// Render the welcome email of a confirmed subscriber.
+///
+/// Returns `None` if welcome emails are disabled or the subscriber is no longer confirmed.
+#[tracing::instrument(skip(transaction, base_url, hmac_secret))]
+pub(crate) async fn render_welcome_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<Option<WelcomeEmail>, anyhow::Error> {
+    let template = get_welcome_email_template(transaction.as_mut()).await?;
+    if !template.enabled {
+        return Ok(None);
+    }
+    let Some(subscriber) = sqlx::query!(
+        r#"
+        SELECT email, name
+        FROM subscriptions
+        WHERE
+            id = $1 AND
+            status = $2
+        "#,
+        subscriber_id,
+        SubscriptionStatus::Confirmed.as_str()
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?
+    else {
+        return Ok(None);
+    };
+    let recipient = SubscriberEmail::parse(subscriber.email).map_err(anyhow::Error::msg)?;
+    let latest_issue = if template.include_latest_issue {
+        get_latest_issue(transaction.as_mut()).await?
+    } else {
+        None
+    };
+
+    let unsubscribe_token = UnsubscribeToken::generate(subscriber_id, hmac_secret);
+    let unsubscribe_url = unsubscribe_url(base_url, &unsubscribe_token);
+    let view_online_url = match &latest_issue {
+        Some(issue) => view_online_url(base_url, issue.newsletter_issue_id),
+        None => format!("{base_url}/issues"),
+    };
+    let context = TemplateContext {
+        subscriber_name: &subscriber.name,
+        subscriber_email: recipient.as_ref(),
+        unsubscribe_url: &unsubscribe_url,
+        view_online_url: &view_online_url,
+    };
+    // Templates are validated when saved, so this only applies to content that predates that.
+    let parse = |content: &str| {
+        IssueTemplate::parse(content).unwrap_or_else(|_| IssueTemplate::verbatim(content))
+    };
+    let mut html_content = parse(&template.html_content).render_html(&context);
+    let mut text_content = parse(&template.text_content).render_text(&context);
+    if let Some(issue) = latest_issue {
+        html_content.push_str(&format!(
+            "\n<hr>\n<h2>{}</h2>\n{}",
+            htmlescape::encode_minimal(&issue.title),
+            parse(&issue.html_content).render_html(&context)
+        ));
+        text_content.push_str(&format!(
+            "\n\n---\n\n{}\n\n{}",
+            issue.title,
+            parse(&issue.text_content).render_text(&context)
+        ));
+    }
+
+    Ok(Some(WelcomeEmail {
+        headers: list_unsubscribe_headers(&unsubscribe_url),
+        recipient,
+        subject: template.subject,
+        html_content,
+        text_content,
+    }))
+}
+
+/// The issue appended to welcome emails.
+struct LatestIssue {
+    newsletter_issue_id: Uuid,
+    title: String,
+    html_content: String,
+    text_content: String,
+}
+
+/// The most recent issue of the public archive.
+#[tracing::instrument(skip_all)]
+async fn get_latest_issue(
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<LatestIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        LatestIssue,
+        r#"
+        SELECT newsletter_issue_id, title, html_content, text_content
+        FROM newsletter_issues
+        WHERE
+            status = $1 AND
+            NOT hidden_from_archive
+        ORDER BY published_at DESC, newsletter_issue_id DESC
+        LIMIT 1
+        "#,
+        IssueStatus::Published.as_str()
+    )
+    .fetch_optional(executor)
+    .await
+}
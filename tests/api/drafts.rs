@@ -0,0 +1,306 @@
+use htmlescape::encode_attribute;
+use wiremock::{
+    matchers::{any, body_partial_json, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{assert_is_redirected_to, create_confirmed_subscriber, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_drafts() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_drafts().await;
+
+    // Assert
+    assert_is_redirected_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn drafts_can_be_saved_edited_and_previewed_without_being_sent() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Save
+    let issue_id = app.create_draft().await;
+
+    // Assert - Part 1
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains("<p><i>The draft has been saved.</i></p>"));
+    assert!(html_page.contains(&format!(
+        r#"value="{}""#,
+        encode_attribute("Newsletter title")
+    )));
+
+    // Act - Part 2 - Edit
+    let response = app
+        .post_update_draft(
+            issue_id,
+            &serde_json::json!({
+                "title": "Edited title",
+                "html_content": "<p>Edited <b>body</b></p>",
+                "text_content": "Edited body <as> plain text.",
+            }),
+        )
+        .await;
+    assert_is_redirected_to(&response, &format!("/admin/drafts/{issue_id}"));
+
+    // Assert - Part 2
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains(&format!(r#"value="{}""#, encode_attribute("Edited title"))));
+
+    // Act - Part 3 - Preview
+    let html_page = app.get_draft_preview_html(issue_id).await;
+
+    // Assert - Part 3
+    assert!(html_page.contains(&format!(
+        r#"srcdoc="{}""#,
+        encode_attribute("<p>Edited <b>body</b></p>")
+    )));
+    assert!(html_page.contains("<pre>Edited body &lt;as&gt; plain text.</pre>"));
+    let n_queued = sqlx::query!("SELECT count(*) FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_queued, Some(0));
+    // Mock verifies no email has been sent
+}
+
+#[tokio::test]
+async fn test_emails_are_only_sent_to_the_logged_in_admin() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let response = app
+        .post_account_email(&serde_json::json!({ "email": "admin@example.com" }))
+        .await;
+    assert_is_redirected_to(&response, "/admin/email");
+    let issue_id = app.create_draft().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "To": "admin@example.com",
+            "Subject": "[Test] Newsletter title"
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_send_test_email(issue_id).await;
+
+    // Assert
+    assert_is_redirected_to(&response, &format!("/admin/drafts/{issue_id}"));
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains("<p><i>A test email has been sent to admin@example.com.</i></p>"));
+    // Mock verifies the draft went to the admin only
+}
+
+#[tokio::test]
+async fn test_emails_carry_a_working_unsubscribe_link() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    app.post_account_email(&serde_json::json!({ "email": "admin@example.com" }))
+        .await;
+    let response = app
+        .post_create_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "text_content": "Unsubscribe: {{ unsubscribe_url }}",
+        }))
+        .await;
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let issue_id = location.rsplit('/').next().unwrap().parse().unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_send_test_email(issue_id).await;
+
+    // Assert
+    let request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let unsubscribe_url = body["TextBody"]
+        .as_str()
+        .unwrap()
+        .strip_prefix("Unsubscribe: ")
+        .unwrap();
+    assert!(unsubscribe_url.contains("/subscriptions/unsubscribe?unsubscribe_token="));
+    let mut unsubscribe_url = reqwest::Url::parse(unsubscribe_url).unwrap();
+    unsubscribe_url.set_port(Some(app.port)).unwrap();
+    let response = reqwest::get(unsubscribe_url).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_emails_require_an_admin_email_address() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    let issue_id = app.create_draft().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_send_test_email(issue_id).await;
+
+    // Assert
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains("before sending a test."));
+}
+
+#[tokio::test]
+async fn published_drafts_are_delivered_and_can_no_longer_be_edited() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let issue_id = app.create_draft().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish
+    let response = app
+        .post_publish_draft(
+            issue_id,
+            &serde_json::json!({ "idempotency_key": uuid::Uuid::new_v4().to_string() }),
+        )
+        .await;
+    assert_is_redirected_to(&response, &format!("/admin/newsletters/{issue_id}"));
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 1
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(html_page.contains("<p><i>The newsletter issue has been published!</i></p>"));
+    assert!(html_page.contains("<tr><th>Sent</th><td>1</td></tr>"));
+
+    // Act - Part 2 - Try to publish and edit again
+    let response = app
+        .post_publish_draft(
+            issue_id,
+            &serde_json::json!({ "idempotency_key": uuid::Uuid::new_v4().to_string() }),
+        )
+        .await;
+    assert_is_redirected_to(&response, "/admin/drafts");
+    let response = app
+        .post_update_draft(
+            issue_id,
+            &serde_json::json!({
+                "title": "Edited title",
+                "html_content": "<p>Edited body</p>",
+                "text_content": "Edited body.",
+            }),
+        )
+        .await;
+
+    // Assert - Part 2
+    assert_is_redirected_to(&response, "/admin/drafts");
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies the issue has been sent exactly once
+}
+
+#[tokio::test]
+async fn drafts_authored_in_markdown_keep_their_source_for_later_edits() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+
+    // Act - Part 1 - Save
+    let response = app
+        .post_create_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown_content": "Hello *world*",
+        }))
+        .await;
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let html_page = app
+        .api_client
+        .get(format!("{}{}", app.address, location))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    assert!(html_page.contains(r#"<textarea name="markdown_content">Hello *world*</textarea>"#));
+    assert!(html_page.contains(
+        r#"<textarea name="html_content">&lt;p&gt;Hello &lt;em&gt;world&lt;/em&gt;&lt;/p&gt;"#
+    ));
+    assert!(html_page.contains(r#"<textarea name="text_content">Hello world</textarea>"#));
+
+    // Act - Part 2 - Preview
+    let issue_id = location.rsplit('/').next().unwrap().parse().unwrap();
+    let html_page = app.get_draft_preview_html(issue_id).await;
+
+    // Assert - Part 2
+    assert!(html_page.contains(&format!(
+        r#"srcdoc="{}""#,
+        encode_attribute("<p>Hello <em>world</em></p>\n")
+    )));
+    assert!(html_page.contains("<pre>Hello world</pre>"));
+}
+
+#[tokio::test]
+async fn drafts_with_invalid_templates_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+
+    // Act
+    let response = app
+        .post_create_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown_content": "Hi {{ <script>alert('hi')</script> }}",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let n_issues = sqlx::query!("SELECT count(*) FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_issues, Some(0));
+}
@@ -0,0 +1,546 @@
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{assert_is_redirected_to, create_confirmed_subscriber, spawn_app, TestApp};
+
+/// Publish an issue right away, returning its id.
+async fn publish_issue(app: &TestApp, title: &str, html_content: &str, hidden: bool) -> Uuid {
+    let mut body = serde_json::json!({
+        "title": title,
+        "html_content": html_content,
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    if hidden {
+        body["hide_from_archive"] = "on".into();
+    }
+    let response = app.post_publish_newsletter(body).await;
+    assert_eq!(response.status().as_u16(), 303);
+    sqlx::query!(
+        "SELECT newsletter_issue_id FROM newsletter_issues WHERE title = $1",
+        title
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .newsletter_issue_id
+}
+
+#[tokio::test]
+async fn published_issues_can_be_read_in_the_public_archive() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    let issue_id = publish_issue(
+        &app,
+        "Public issue",
+        "<p>Hi {{ subscriber.name }}</p>",
+        false,
+    )
+    .await;
+    app.post_logout().await;
+
+    // Act - Part 1 - List
+    let html_page = app.get_archive_html(1).await;
+
+    // Assert - Part 1
+    assert!(html_page.contains(&format!(r#"<a href="/issues/{issue_id}">Public issue</a>"#)));
+
+    // Act - Part 2 - Read
+    let response = app.get_archived_issue(issue_id).await;
+
+    // Assert - Part 2
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>Public issue</h1>"));
+    assert!(html_page.contains("<p>Hi reader</p>"));
+}
+
+#[tokio::test]
+async fn issues_hidden_from_the_archive_are_neither_listed_nor_readable() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    let hidden_id = publish_issue(&app, "Hidden issue", "<p>Secret</p>", true).await;
+    let draft_id = app.create_draft().await;
+
+    // Act
+    let html_page = app.get_archive_html(1).await;
+
+    // Assert
+    assert!(!html_page.contains("Hidden issue"));
+    assert!(!html_page.contains("Newsletter title"));
+    assert_eq!(
+        app.get_archived_issue(hidden_id).await.status().as_u16(),
+        404
+    );
+    assert_eq!(
+        app.get_archived_issue(draft_id).await.status().as_u16(),
+        404
+    );
+    assert!(app
+        .get_newsletter_issue_html(hidden_id)
+        .await
+        .contains("Hidden from the public archive."));
+}
+
+#[tokio::test]
+async fn cancelled_issues_stay_readable_for_the_recipients_who_got_them() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let delivered_id = publish_issue(&app, "Partially delivered", "<p>Hi</p>", false).await;
+    let undelivered_id = publish_issue(&app, "Never delivered", "<p>Hi</p>", false).await;
+    // Simulate the worker getting through one recipient before the cancellation
+    let delivered_to = sqlx::query!(
+        "DELETE FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1 AND subscriber_email = (
+            SELECT min(subscriber_email) FROM issue_delivery_queue WHERE newsletter_issue_id = $1
+        )
+        RETURNING subscriber_email",
+        delivered_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .subscriber_email;
+    sqlx::query!(
+        "UPDATE issue_deliveries SET status = 'sent'
+        WHERE newsletter_issue_id = $1 AND subscriber_email = $2",
+        delivered_id,
+        delivered_to
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    for issue_id in [delivered_id, undelivered_id] {
+        let response = app.post_cancel_newsletter_issue(issue_id).await;
+        assert_is_redirected_to(&response, &format!("/admin/newsletters/{issue_id}"));
+    }
+    app.post_logout().await;
+
+    // Assert
+    let response = app.get_archived_issue(delivered_id).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("<h1>Partially delivered</h1>"));
+    assert_eq!(
+        app.get_archived_issue(undelivered_id)
+            .await
+            .status()
+            .as_u16(),
+        404
+    );
+}
+
+#[tokio::test]
+async fn the_archive_is_paginated_newest_first() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    for i in 0..11 {
+        publish_issue(&app, &format!("Issue #{i:02}"), "<p>Body</p>", false).await;
+    }
+
+    // Act
+    let first_page = app.get_archive_html(1).await;
+    let second_page = app.get_archive_html(2).await;
+
+    // Assert
+    assert!(first_page.contains("Issue #10"));
+    assert!(first_page.contains("Issue #01"));
+    assert!(!first_page.contains("Issue #00"));
+    assert!(first_page.contains(r#"<a href="/issues?page=2">Older issues</a>"#));
+    assert!(second_page.contains("Issue #00"));
+    assert!(!second_page.contains("Issue #01"));
+    assert!(second_page.contains(r#"<a href="/issues?page=1">Newer issues</a>"#));
+    assert!(!second_page.contains("Older issues"));
+}
+
+#[tokio::test]
+async fn delivered_issues_link_to_their_online_version() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let issue_id = publish_issue(
+        &app,
+        "Linked issue",
+        r#"<a href="{{ view_online_url }}">View online</a>"#,
+        false,
+    )
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(
+        body["HtmlBody"].as_str().unwrap(),
+        format!(
+            r#"<a href="{}">View online</a>"#,
+            htmlescape::encode_attribute(&format!("{}/issues/{}", app.base_url, issue_id))
+        )
+    );
+}
+
+#[tokio::test]
+async fn hidden_issues_cannot_link_to_their_online_version() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(serde_json::json!({
+            "title": "Newsletter title",
+            "html_content": r#"<a href="{{ view_online_url }}">View online</a>"#,
+            "text_content": "Newsletter body as plain text",
+            "hide_from_archive": "on",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn archived_issues_are_syndicated_in_rss_and_atom_feeds() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    let issue_id = publish_issue(
+        &app,
+        "Tom & Jerry <3",
+        "<p>Hi {{ subscriber.name }}</p>",
+        false,
+    )
+    .await;
+    publish_issue(&app, "Hidden issue", "<p>Secret</p>", true).await;
+
+    for (feed, content_type, escaped_content) in [
+        (
+            "feed.rss",
+            "application/rss+xml; charset=utf-8",
+            "<description>&lt;p&gt;Hi reader&lt;/p&gt;</description>",
+        ),
+        (
+            "feed.atom",
+            "application/atom+xml; charset=utf-8",
+            r#"<content type="html">&lt;p&gt;Hi reader&lt;/p&gt;</content>"#,
+        ),
+    ] {
+        // Act
+        let response = app.get_feed(feed).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 200);
+        let headers = response.headers();
+        assert_eq!(headers["Content-Type"], content_type);
+        assert_eq!(headers["Cache-Control"], "public, max-age=300");
+        assert!(headers.contains_key("ETag"));
+        let body = response.text().await.unwrap();
+        assert!(body.contains("<title>Tom &amp; Jerry &lt;3</title>"));
+        assert!(body.contains(&format!("{}/issues/{}", app.base_url, issue_id)));
+        assert!(body.contains(escaped_content), "{feed}: {body}");
+        assert!(!body.contains("Hidden issue"));
+    }
+}
+
+#[tokio::test]
+async fn unchanged_feeds_are_not_sent_again() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    publish_issue(&app, "First issue", "<p>Body</p>", false).await;
+    let etag = app.get_feed("feed.atom").await.headers()["ETag"].clone();
+
+    // Act - Part 1 - Unchanged
+    let response = app
+        .api_client
+        .get(format!("{}/feed.atom", app.address))
+        .header("If-None-Match", etag.clone())
+        .send()
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 304);
+
+    // Act - Part 2 - A new issue
+    publish_issue(&app, "Second issue", "<p>Body</p>", false).await;
+    let response = app
+        .api_client
+        .get(format!("{}/feed.atom", app.address))
+        .header("If-None-Match", etag)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Second issue"));
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_list_published_issues() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_admin_issues(&[("published_from", "")]).await;
+
+    // Assert
+    assert_is_redirected_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn admins_can_list_published_issues_by_publish_date() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    for (title, published_at) in [
+        ("October issue", "2023-10-31T23:30:00Z"),
+        ("Early November issue", "2023-11-01T08:00:00Z"),
+        ("Late November issue", "2023-11-30T20:00:00Z"),
+    ] {
+        let issue_id = publish_issue(&app, title, "<p>Body</p>", false).await;
+        sqlx::query!(
+            "UPDATE newsletter_issues SET published_at = $2::text::timestamptz WHERE newsletter_issue_id = $1",
+            issue_id,
+            published_at
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+    app.create_draft().await;
+
+    // Act - Part 1 - Everything
+    let html_page = app
+        .get_admin_issues(&[("published_from", ""), ("published_until", "")])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    let late = html_page.find("Late November issue").unwrap();
+    let early = html_page.find("Early November issue").unwrap();
+    let october = html_page.find("October issue").unwrap();
+    assert!(
+        late < early && early < october,
+        "Issues are not newest first"
+    );
+    assert!(!html_page.contains("Newsletter title"));
+
+    // Act - Part 2 - Filtered
+    let html_page = app
+        .get_admin_issues(&[
+            ("published_from", "2023-11-01"),
+            ("published_until", "2023-11-01"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert!(html_page.contains("Early November issue"));
+    assert!(!html_page.contains("October issue"));
+    assert!(!html_page.contains("Late November issue"));
+
+    // Act - Part 3 - Invalid
+    let response = app
+        .get_admin_issues(&[("published_from", "yesterday")])
+        .await;
+
+    // Assert - Part 3
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn published_issues_show_their_delivery_progress() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Published
+    publish_issue(&app, "Newsletter title", "<p>Body</p>", false).await;
+    let html_page = app
+        .get_admin_issues(&[("q", "")])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    assert!(html_page.contains("<td>1</td>"));
+    assert!(html_page.contains("<td>0 sent, 1 pending</td>"));
+
+    // Act - Part 2 - Delivered
+    app.dispatch_all_pending_emails().await;
+    let html_page = app
+        .get_admin_issues(&[("q", "")])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert!(html_page.contains("<td>1 sent, 0 pending</td>"));
+}
+
+#[tokio::test]
+async fn published_issues_can_be_searched_and_paged_through() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    for i in 1..=25 {
+        let text_content = if i % 2 == 1 {
+            "All about Kittens."
+        } else {
+            "All about puppies."
+        };
+        sqlx::query!(
+            r#"
+            INSERT INTO newsletter_issues (
+                newsletter_issue_id,
+                title,
+                text_content,
+                html_content,
+                published_at,
+                status
+            )
+            VALUES ($1, $2, $3, '<p>Body</p>', now() - make_interval(days => 30 - $4), 'published')
+            "#,
+            Uuid::new_v4(),
+            format!("Issue #{i:02}"),
+            text_content,
+            i
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+
+    // Act - Part 1 - Search
+    let html_page = app
+        .get_admin_issues(&[("q", "KITTENS")])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    assert_eq!(html_page.matches("<td><a href=").count(), 13);
+    assert!(!html_page.contains("Issue #02"));
+    assert!(!html_page.contains("Older issues"));
+
+    // Act - Part 2 - First page
+    let html_page = app
+        .get_admin_issues(&[("q", "")])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert_eq!(html_page.matches("<td><a href=").count(), 20);
+    assert!(html_page.contains("Issue #25"));
+    assert!(html_page.contains("Issue #06"));
+    assert!(!html_page.contains("Issue #05"));
+
+    // Act - Part 3 - Next page
+    let (before_link_text, _) = html_page.split_once(r#"">Older issues"#).unwrap();
+    let (_, next_page) = before_link_text.rsplit_once(r#"href=""#).unwrap();
+    let next_page = next_page.replace("&amp;", "&");
+    let html_page = app
+        .api_client
+        .get(format!("{}{}", app.address, next_page))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 3
+    assert_eq!(html_page.matches("<td><a href=").count(), 5);
+    assert!(html_page.contains("Issue #05"));
+    assert!(html_page.contains("Issue #01"));
+    assert!(!html_page.contains("Older issues"));
+    assert!(html_page.contains("Newest issues"));
+}
+
+#[tokio::test]
+async fn search_covers_the_markdown_source_of_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            published_at,
+            status
+        )
+        VALUES ($1, 'Markdown issue', 'See the docs', '<p>See the docs</p>',
+            'See [the docs](https://example.com/kittens)', now(), 'published')
+        "#,
+        Uuid::new_v4()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let html_page = app
+        .get_admin_issues(&[("q", "example.com/KITTENS")])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(html_page.matches("<td><a href=").count(), 1);
+    assert!(html_page.contains("Markdown issue"));
+}
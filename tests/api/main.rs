@@ -1,8 +1,15 @@
 mod admin_dashboard;
 mod change_password;
+mod drafts;
 mod health_check;
 mod helpers;
+mod issues;
 mod login;
 mod newsletters;
+mod shutdown;
+mod subscribers;
+mod subscription_cleanup;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod welcome_email;
@@ -1,13 +1,15 @@
-use fake::faker::internet::en::SafeEmail;
-use fake::faker::name::en::Name;
-use fake::Fake;
+use sqlx::postgres::PgListener;
 use std::time::Duration;
 use wiremock::{
     matchers::{any, method, path},
     Mock, ResponseTemplate,
 };
+use zero_to_prod::issue_delivery_worker::{try_execute_task, DELIVERY_QUEUE_CHANNEL};
+use zero_to_prod::issue_scheduler::publish_due_issues;
 
-use crate::helpers::{assert_is_redirected_to, spawn_app, ConfirmationLinks, TestApp};
+use crate::helpers::{
+    assert_is_redirected_to, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+};
 
 #[tokio::test]
 async fn user_must_be_logged_in_to_see_newsletters_form() {
@@ -72,37 +74,6 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     // Mock verifies receiving no requests
 }
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let _mock_guard = Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber.")
-        .expect(1)
-        .mount_as_scoped(&app.email_server)
-        .await;
-    let name = Name().fake::<String>();
-    let email = SafeEmail().fake::<String>();
-    let body = serde_urlencoded::to_string(serde_json::json!({
-        "name": name,
-        "email": email
-    }))
-    .unwrap();
-    app.post_subscriptions(body)
-        .await
-        .error_for_status()
-        .unwrap();
-
-    let email_request = &app
-        .email_server
-        .received_requests()
-        .await
-        .unwrap()
-        .pop()
-        .unwrap();
-
-    app.get_confirmation_links(email_request)
-}
-
 #[tokio::test]
 async fn newsletters_are_delivered_to_confirmed_subscribers() {
     // Arrange
@@ -135,15 +106,6 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     // Mock verifies receiving one request
 }
 
-async fn create_confirmed_subscriber(app: &TestApp) {
-    let confirmation_link = create_unconfirmed_subscriber(app).await;
-    reqwest::get(confirmation_link.html)
-        .await
-        .unwrap()
-        .error_for_status()
-        .unwrap();
-}
-
 #[tokio::test]
 async fn newsletters_returns_400_for_invalid_body() {
     // Arrange
@@ -255,3 +217,904 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     app.dispatch_all_pending_emails().await;
     // Mock verifies on drop that we have only sent the newsletter once
 }
+
+#[tokio::test]
+async fn newsletter_emails_carry_one_click_unsubscribe_headers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish and deliver an issue
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 1 - Headers are present
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let headers = body["Headers"].as_array().unwrap();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|h| h["Name"] == name)
+            .and_then(|h| h["Value"].as_str())
+            .unwrap()
+            .to_owned()
+    };
+    assert_eq!(
+        header("List-Unsubscribe-Post"),
+        "List-Unsubscribe=One-Click"
+    );
+    let list_unsubscribe = header("List-Unsubscribe");
+    let mut unsubscribe_link = reqwest::Url::parse(
+        list_unsubscribe
+            .strip_prefix('<')
+            .and_then(|l| l.strip_suffix('>'))
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(unsubscribe_link.host_str().unwrap(), "127.0.0.1");
+    unsubscribe_link.set_port(Some(app.port)).unwrap();
+
+    // Act - Part 2 - One-click unsubscribe, as a mailbox provider would
+    let response = reqwest::Client::new()
+        .post(unsubscribe_link)
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn transient_delivery_failures_are_retried() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let n_dead_letters = sqlx::query!("SELECT count(*) FROM issue_delivery_dead_letters")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_dead_letters, Some(0));
+    // Mocks verify one failed and one successful attempt
+}
+
+#[tokio::test]
+async fn deliveries_exceeding_the_retry_budget_are_dead_lettered_and_can_be_requeued() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let max_attempts = u64::try_from(app.worker_settings.max_retries).unwrap() + 1;
+
+    let failing_mock = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(max_attempts)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Exhaust the retry budget
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+    drop(failing_mock);
+
+    // Assert - Part 1
+    let dead_letter = sqlx::query!(
+        "SELECT newsletter_issue_id, subscriber_email, n_retries \
+        FROM issue_delivery_dead_letters"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(dead_letter.n_retries, app.worker_settings.max_retries);
+    let html_page = app.get_dead_letters_html().await;
+    assert!(html_page.contains(&dead_letter.subscriber_email));
+
+    // Act - Part 2 - Re-enqueue the delivery
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_requeue_dead_letter(&serde_json::json!({
+            "newsletter_issue_id": dead_letter.newsletter_issue_id,
+            "subscriber_email": dead_letter.subscriber_email,
+        }))
+        .await;
+    assert_is_redirected_to(&response, "/admin/dead_letters");
+    let html_page = app.get_dead_letters_html().await;
+    assert!(html_page.contains("<p><i>The delivery has been re-enqueued.</i></p>"));
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let n_dead_letters = sqlx::query!("SELECT count(*) FROM issue_delivery_dead_letters")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_dead_letters, Some(0));
+    // Mock verifies the re-enqueued delivery was sent
+}
+
+#[tokio::test]
+async fn dead_letters_of_unsubscribed_recipients_are_not_requeued() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let failing_mock = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+    drop(failing_mock);
+    let dead_letter = sqlx::query!(
+        "SELECT newsletter_issue_id, subscriber_email FROM issue_delivery_dead_letters"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    app.post_subscriber_action(subscriber_id, "unsubscribe")
+        .await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_requeue_dead_letter(&serde_json::json!({
+            "newsletter_issue_id": dead_letter.newsletter_issue_id,
+            "subscriber_email": dead_letter.subscriber_email,
+        }))
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_is_redirected_to(&response, "/admin/dead_letters");
+    let html_page = app.get_dead_letters_html().await;
+    assert!(html_page.contains(
+        "<p><i>The delivery was not re-enqueued: the recipient is no longer subscribed.</i></p>"
+    ));
+    let n_queued = sqlx::query!("SELECT count(*) FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_queued, Some(0));
+    let delivery = sqlx::query!("SELECT status FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "skipped_unsubscribed");
+}
+
+#[tokio::test]
+async fn queued_deliveries_are_skipped_once_the_recipient_is_no_longer_confirmed() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    sqlx::query!("UPDATE subscriptions SET status = 'pending_confirmation'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let delivery = sqlx::query!("SELECT status FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "skipped_unsubscribed");
+}
+
+#[tokio::test]
+async fn queued_deliveries_to_deleted_subscribers_are_recorded_as_such() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    sqlx::query!("DELETE FROM subscription_tokens")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query!("DELETE FROM subscriptions")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let delivery = sqlx::query!("SELECT status FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "skipped_missing_subscriber");
+}
+
+#[tokio::test]
+async fn delivery_progress_is_tracked_per_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817"
+        })))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+
+    // Assert - Part 1
+    let html_page = app.get_newsletters_html().await;
+    assert!(html_page.contains(&format!(r#"href="/admin/newsletters/{issue_id}""#)));
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(html_page.contains("<tr><th>Pending</th><td>1</td></tr>"));
+    assert!(html_page.contains("Delivery in progress..."));
+
+    // Act - Part 2 - Deliver
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(html_page.contains("<tr><th>Pending</th><td>0</td></tr>"));
+    assert!(html_page.contains("<tr><th>Sent</th><td>1</td></tr>"));
+    assert!(html_page.contains("Delivery finished."));
+    let delivery = sqlx::query!("SELECT status, provider_message_id FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "sent");
+    assert_eq!(
+        delivery.provider_message_id.as_deref(),
+        Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+    );
+}
+
+#[tokio::test]
+async fn partial_batch_failures_are_retried_per_recipient() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            {
+                "ErrorCode": 0,
+                "Message": "OK",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817"
+            },
+            {
+                "ErrorCode": 406,
+                "Message": "You tried to send to a recipient that has been marked as inactive."
+            }
+        ])))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    // Only the recipient that failed is retried, on its own
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish and send the first batch
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    try_execute_task(
+        &app.db_pool,
+        &app.email_client,
+        &app.base_url,
+        &app.hmac_secret,
+        &app.worker_settings,
+        &app.rate_limiter,
+    )
+    .await
+    .unwrap();
+
+    // Assert - Part 1
+    let deliveries =
+        sqlx::query!("SELECT status, last_error FROM issue_deliveries ORDER BY status")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(deliveries.len(), 2);
+    assert_eq!(deliveries[0].status, "queued");
+    assert!(deliveries[0]
+        .last_error
+        .as_deref()
+        .unwrap()
+        .contains("marked as inactive"));
+    assert_eq!(deliveries[1].status, "sent");
+
+    // Act - Part 2 - Retry
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let n_sent = sqlx::query!("SELECT count(*) FROM issue_deliveries WHERE status = 'sent'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_sent, Some(2));
+}
+
+#[tokio::test]
+async fn publishing_an_issue_wakes_up_idle_workers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let mut listener = PgListener::connect_with(&app.db_pool).await.unwrap();
+    listener.listen(DELIVERY_QUEUE_CHANNEL).await.unwrap();
+
+    // Act
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+
+    // Assert
+    let notification = tokio::time::timeout(Duration::from_secs(5), listener.recv())
+        .await
+        .expect("No worker was notified of the new deliveries.")
+        .unwrap();
+    assert_eq!(notification.channel(), DELIVERY_QUEUE_CHANNEL);
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_only_delivered_once_their_send_time_arrives() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let send_at = chrono::Utc::now() + chrono::Duration::hours(1);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Schedule
+    let response = app
+        .post_publish_newsletter(serde_json::json!({
+            "title": "Newsletter title",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "text_content": "Newsletter body as plain text.",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            "send_at": send_at.to_rfc3339(),
+        }))
+        .await;
+    assert_is_redirected_to(&response, "/admin/newsletters");
+    publish_due_issues(&app.db_pool).await.unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 1
+    let html_page = app.get_newsletters_html().await;
+    assert!(html_page.contains("The newsletter issue has been scheduled for"));
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(html_page.contains("Scheduled for"));
+    let n_queued = sqlx::query!("SELECT count(*) FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_queued, Some(0));
+
+    // Act - Part 2 - Let the send time pass
+    sqlx::query!("UPDATE newsletter_issues SET scheduled_for = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let published = publish_due_issues(&app.db_pool).await.unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    assert_eq!(published, vec![issue_id]);
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(html_page.contains("<tr><th>Sent</th><td>1</td></tr>"));
+    // Mock verifies that the newsletter has been sent exactly once
+}
+
+#[tokio::test]
+async fn cancelled_issues_are_never_delivered() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let send_at = chrono::Utc::now() + chrono::Duration::hours(1);
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Schedule and cancel
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        "send_at": send_at.to_rfc3339(),
+    }))
+    .await;
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    let response = app.post_cancel_newsletter_issue(issue_id).await;
+    assert_is_redirected_to(&response, &format!("/admin/newsletters/{issue_id}"));
+
+    // Assert - Part 1
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(html_page.contains("<p><i>The newsletter issue has been cancelled.</i></p>"));
+    assert!(html_page.contains("<p>Cancelled.</p>"));
+
+    // Act - Part 2 - Let the send time pass
+    sqlx::query!("UPDATE newsletter_issues SET scheduled_for = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let published = publish_due_issues(&app.db_pool).await.unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    assert!(published.is_empty());
+    // Mock verifies no newsletter has been sent
+}
+
+#[tokio::test]
+async fn send_times_in_the_past_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(serde_json::json!({
+            "title": "Newsletter title",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "text_content": "Newsletter body as plain text.",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            "send_at": "2020-01-01T00:00",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn paused_issues_are_not_delivered_until_resumed() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish and pause
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    let response = app.post_pause_newsletter_issue(issue_id).await;
+    assert_is_redirected_to(&response, &format!("/admin/newsletters/{issue_id}"));
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 1
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(html_page.contains("<p><i>The delivery has been paused.</i></p>"));
+    assert!(html_page.contains("<p>Delivery paused.</p>"));
+    assert!(html_page.contains("<tr><th>Pending</th><td>1</td></tr>"));
+
+    // Act - Part 2 - Resume
+    app.post_resume_newsletter_issue(issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(html_page.contains("<p><i>The delivery has been resumed.</i></p>"));
+    assert!(html_page.contains("<tr><th>Sent</th><td>1</td></tr>"));
+    // Mock verifies the issue has been sent exactly once
+}
+
+#[tokio::test]
+async fn issues_whose_delivery_has_finished_cannot_be_cancelled() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+
+    // Act
+    let response = app.post_cancel_newsletter_issue(issue_id).await;
+
+    // Assert
+    assert_is_redirected_to(&response, &format!("/admin/newsletters/{issue_id}"));
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(
+        html_page.contains("<p><i>Only scheduled or undelivered issues can be cancelled.</i></p>")
+    );
+    assert!(html_page.contains("<p>Delivery finished.</p>"));
+    let status = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .status;
+    assert_eq!(status, "published");
+}
+
+#[tokio::test]
+async fn cancelling_an_issue_mid_delivery_records_the_recipients_left_out() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    let response = app.post_cancel_newsletter_issue(issue_id).await;
+    assert_is_redirected_to(&response, &format!("/admin/newsletters/{issue_id}"));
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let html_page = app.get_newsletter_issue_html(issue_id).await;
+    assert!(html_page.contains("<p><i>The newsletter issue has been cancelled.</i></p>"));
+    assert!(html_page.contains("<tr><th>Pending</th><td>0</td></tr>"));
+    assert!(html_page.contains("<tr><th>Cancelled</th><td>2</td></tr>"));
+    let n_queued = sqlx::query!("SELECT count(*) FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_queued, Some(0));
+    // Mock verifies no email has been sent
+}
+
+#[tokio::test]
+async fn issue_bodies_are_personalised_for_each_recipient() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let subscriber = sqlx::query!("SELECT name, email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": r#"<p>Hi {{ subscriber.name }}!</p><a href="{{ unsubscribe_url }}">Leave</a>"#,
+        "text_content": "Hi {{ subscriber.name }}, this was sent to {{ subscriber.email }}.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    let text_body = body["TextBody"].as_str().unwrap();
+    assert!(html_body.contains(&format!(
+        "<p>Hi {}!</p>",
+        htmlescape::encode_attribute(&subscriber.name)
+    )));
+    assert!(html_body.contains(&format!(
+        r#"<a href="{}"#,
+        htmlescape::encode_attribute(&format!(
+            "{}/subscriptions/unsubscribe?unsubscribe_token=",
+            app.base_url
+        ))
+    )));
+    assert_eq!(
+        text_body,
+        format!(
+            "Hi {}, this was sent to {}.",
+            subscriber.name, subscriber.email
+        )
+    );
+}
+
+#[tokio::test]
+async fn issues_with_invalid_templates_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let test_cases = vec![
+        ("<p>Hi {{ subscriber.age }}</p>", "Hi!", "unknown variable"),
+        (
+            "<p>Hi!</p>",
+            "Hi {{ subscriber.name",
+            "unclosed placeholder",
+        ),
+    ];
+
+    for (html_content, text_content, error_message) in test_cases {
+        // Act
+        let response = app
+            .post_publish_newsletter(serde_json::json!({
+                "title": "Newsletter title",
+                "html_content": html_content,
+                "text_content": text_content,
+                "idempotency_key": uuid::Uuid::new_v4().to_string()
+            }))
+            .await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload had an {}.",
+            error_message
+        );
+    }
+    let n_issues = sqlx::query!("SELECT count(*) FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_issues, Some(0));
+}
+
+#[tokio::test]
+async fn issues_authored_in_markdown_are_sent_as_html_and_plain_text() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let markdown_content =
+        "# News\n\nRead [the docs](https://example.com/docs).\n\n<script>alert('hi')</script>";
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "markdown_content": markdown_content,
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    assert!(html_body.contains("<h1>News</h1>"));
+    assert!(html_body.contains(r#"<a href="https://example.com/docs""#));
+    assert!(!html_body.contains("<script>"));
+    assert_eq!(
+        body["TextBody"].as_str().unwrap(),
+        "News\n\nRead the docs (https://example.com/docs)."
+    );
+    let saved = sqlx::query!("SELECT markdown_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.markdown_content.as_deref(), Some(markdown_content));
+}
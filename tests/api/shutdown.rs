@@ -0,0 +1,85 @@
+use std::time::Duration;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{create_confirmed_subscriber, spawn_app};
+
+#[tokio::test]
+async fn the_api_stops_accepting_connections_on_shutdown() {
+    // Arrange
+    let app = spawn_app().await;
+    // Keep-alive connections are drained rather than dropped: probe with fresh ones
+    let client = reqwest::Client::builder()
+        .pool_max_idle_per_host(0)
+        .build()
+        .unwrap();
+
+    // Act
+    app.shutdown.cancel();
+
+    // Assert
+    let mut refused = false;
+    for _ in 0..50 {
+        if client
+            .get(format!("{}/health_check", &app.address))
+            .send()
+            .await
+            .is_err()
+        {
+            refused = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(refused, "The API kept serving requests after shutdown.");
+}
+
+#[tokio::test]
+async fn the_worker_finishes_pending_deliveries_and_exits_on_shutdown() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let worker = app.spawn_worker();
+
+    // Act - Part 1 - Publish and let the worker pick the delivery up
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text.",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+    let mut delivered = false;
+    for _ in 0..50 {
+        let status = sqlx::query!("SELECT status FROM issue_deliveries")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .status;
+        if status == "sent" {
+            delivered = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Act - Part 2 - Shut down
+    app.shutdown.cancel();
+    let outcome = tokio::time::timeout(Duration::from_secs(5), worker)
+        .await
+        .expect("The worker did not exit after shutdown was requested.")
+        .unwrap();
+
+    // Assert
+    assert!(delivered);
+    assert!(outcome.is_ok());
+}
@@ -0,0 +1,174 @@
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{
+    assert_is_redirected_to, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+    TestApp,
+};
+
+struct StoredSubscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+}
+
+async fn get_subscribers(app: &TestApp) -> Vec<StoredSubscriber> {
+    sqlx::query_as!(
+        StoredSubscriber,
+        "SELECT id, email, name, status FROM subscriptions ORDER BY subscribed_at"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let list = app
+        .api_client
+        .get(format!("{}/admin/subscribers", app.address))
+        .send()
+        .await
+        .unwrap();
+    let delete = app.post_subscriber_action(Uuid::new_v4(), "delete").await;
+
+    // Assert
+    assert_is_redirected_to(&list, "/login");
+    assert_is_redirected_to(&delete, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_searched_and_filtered_by_status() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_unconfirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let subscribers = get_subscribers(&app).await;
+    let (confirmed, pending) = (&subscribers[0], &subscribers[1]);
+
+    // Act - Part 1 - Everyone
+    let html_page = app.get_subscribers_html(&[("q", "")]).await;
+
+    // Assert - Part 1
+    assert!(html_page.contains(&format!(
+        r#"<a href="/admin/subscribers/{}">"#,
+        confirmed.id
+    )));
+    assert!(html_page.contains(&format!(r#"<a href="/admin/subscribers/{}">"#, pending.id)));
+
+    // Act - Part 2 - Search, ignoring case
+    let html_page = app
+        .get_subscribers_html(&[("q", pending.email.to_uppercase())])
+        .await;
+
+    // Assert - Part 2
+    assert!(html_page.contains(&pending.id.to_string()));
+    assert!(!html_page.contains(&confirmed.id.to_string()));
+
+    // Act - Part 3 - Status
+    let html_page = app
+        .get_subscribers_html(&[("q", ""), ("status", "confirmed")])
+        .await;
+
+    // Assert - Part 3
+    assert!(html_page.contains(&confirmed.id.to_string()));
+    assert!(!html_page.contains(&pending.id.to_string()));
+    assert!(html_page.contains(r#"<option value="confirmed" selected>"#));
+}
+
+#[tokio::test]
+async fn subscriber_details_show_their_history() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let subscriber = get_subscribers(&app).await.pop().unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_publish_newsletter(serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let html_page = app.get_subscriber_html(subscriber.id).await;
+
+    // Assert
+    assert!(html_page.contains(&htmlescape::encode_minimal(&subscriber.name)));
+    assert!(html_page.contains("1 confirmation email(s) sent"));
+    assert!(html_page.contains("Confirmed at"));
+    assert!(html_page.contains("Newsletter title</a></td>\n            <td>sent</td>"));
+    assert!(!html_page.contains("/confirm"));
+    assert_eq!(
+        app.get_subscriber(Uuid::new_v4()).await.status().as_u16(),
+        404
+    );
+}
+
+#[tokio::test]
+async fn admins_can_confirm_unsubscribe_and_delete_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    let subscriber_id = get_subscribers(&app).await[0].id;
+    let detail_page = format!("/admin/subscribers/{subscriber_id}");
+
+    // Act - Part 1 - Confirm
+    let response = app.post_subscriber_action(subscriber_id, "confirm").await;
+
+    // Assert - Part 1
+    assert_is_redirected_to(&response, &detail_page);
+    assert_eq!(get_subscribers(&app).await[0].status, "confirmed");
+    let html_page = app.get_subscriber_html(subscriber_id).await;
+    assert!(html_page.contains("<p><i>The subscriber has been confirmed.</i></p>"));
+    assert!(html_page.contains("Confirmed at"));
+
+    // Act - Part 2 - Unsubscribe
+    let response = app
+        .post_subscriber_action(subscriber_id, "unsubscribe")
+        .await;
+
+    // Assert - Part 2
+    assert_is_redirected_to(&response, &detail_page);
+    assert_eq!(get_subscribers(&app).await[0].status, "unsubscribed");
+    let html_page = app.get_subscriber_html(subscriber_id).await;
+    assert!(html_page.contains("Unsubscribed at"));
+
+    // Act - Part 3 - Confirming again is refused
+    app.post_subscriber_action(subscriber_id, "confirm").await;
+
+    // Assert - Part 3
+    assert_eq!(get_subscribers(&app).await[0].status, "unsubscribed");
+
+    // Act - Part 4 - Delete
+    let response = app.post_subscriber_action(subscriber_id, "delete").await;
+
+    // Assert - Part 4
+    assert_is_redirected_to(&response, "/admin/subscribers");
+    assert!(get_subscribers(&app).await.is_empty());
+    let n_tokens = sqlx::query!("SELECT count(*) FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_tokens, Some(0));
+}
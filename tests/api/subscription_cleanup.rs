@@ -0,0 +1,124 @@
+use chrono::Utc;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+use zero_to_prod::subscription_cleanup::purge_abandoned_subscriptions;
+
+use crate::helpers::{create_confirmed_subscriber, spawn_app, TestApp};
+
+#[tokio::test]
+async fn pending_subscriptions_older_than_the_retention_period_are_purged() {
+    // Arrange
+    let app = spawn_app().await;
+    create_pending_subscriber(&app, "ursula_le_guin@gmail.com").await;
+    create_pending_subscriber(&app, "octavia_butler@gmail.com").await;
+    let retention = app.configuration.subscriptions.pending_retention();
+    backdate_subscriber(&app, "ursula_le_guin@gmail.com", retention).await;
+
+    // Act
+    let n_purged = purge_abandoned_subscriptions(&app.db_pool, retention)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(n_purged, 1);
+    let remaining = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].email, "octavia_butler@gmail.com");
+    let n_tokens = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_tokens, 1);
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_are_never_purged() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let retention = app.configuration.subscriptions.pending_retention();
+    sqlx::query!(
+        "UPDATE subscriptions SET subscribed_at = $1",
+        Utc::now() - retention - chrono::Duration::days(1)
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let n_purged = purge_abandoned_subscriptions(&app.db_pool, retention)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(n_purged, 0);
+}
+
+#[tokio::test]
+async fn old_pending_subscriptions_that_asked_for_a_new_link_are_kept() {
+    // Arrange
+    let app = spawn_app().await;
+    create_pending_subscriber(&app, "ursula_le_guin@gmail.com").await;
+    let retention = app.configuration.subscriptions.pending_retention();
+    backdate_subscriber(&app, "ursula_le_guin@gmail.com", retention).await;
+    // Signing up again sends a fresh token
+    create_pending_subscriber(&app, "ursula_le_guin@gmail.com").await;
+
+    // Act
+    let n_purged = purge_abandoned_subscriptions(&app.db_pool, retention)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(n_purged, 0);
+}
+
+async fn create_pending_subscriber(app: &TestApp, email: &str) {
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .named("Create pending subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(format!(
+        "name=le%20guin&email={}",
+        urlencoding::encode(email)
+    ))
+    .await
+    .error_for_status()
+    .unwrap();
+    app.dispatch_all_pending_emails().await;
+}
+
+/// Move the sign-up, and every confirmation email sent since, past the retention period.
+async fn backdate_subscriber(app: &TestApp, email: &str, retention: chrono::Duration) {
+    let long_ago = Utc::now() - retention - chrono::Duration::days(1);
+    sqlx::query!(
+        "UPDATE subscriptions
+        SET subscribed_at = $1, confirmation_requested_at = $1
+        WHERE email = $2",
+        long_ago,
+        email
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+        UPDATE subscription_tokens SET created_at = $1
+        WHERE subscriber_id = (SELECT id FROM subscriptions WHERE email = $2)
+        "#,
+        long_ago,
+        email
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
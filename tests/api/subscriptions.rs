@@ -1,9 +1,10 @@
 use wiremock::{
-    matchers::{method, path},
+    matchers::{any, method, path},
     Mock, ResponseTemplate,
 };
+use zero_to_prod::{email_outbox::try_send_outbox_emails, issue_delivery_worker::ExecutionOutcome};
 
-use crate::helpers::spawn_app;
+use crate::helpers::{assert_is_redirected_to, spawn_app};
 
 #[tokio::test]
 async fn subscribe_returns_a_200_for_valid_form_data() {
@@ -37,6 +38,7 @@ async fn subscribe_persist_the_new_subscriber() {
 
     // Act
     app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
 
     // Assert
     let saved = sqlx::query!("SELECT email, name, status FROM subscriptions")
@@ -110,6 +112,7 @@ async fn subscribe_sends_a_confirmation_email_for_valid_data() {
 
     // Act
     app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
 
     // Assert
     // Mock asserts on drop
@@ -129,6 +132,7 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
 
     // Act
     app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
 
     // Assert
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
@@ -137,6 +141,85 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
     assert_eq!(confirmation_links.html, confirmation_links.plain_text);
 }
 
+#[tokio::test]
+async fn subscribe_does_not_wait_for_the_email_provider() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let outbox = sqlx::query!("SELECT recipient, subject FROM email_outbox")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(outbox.len(), 1);
+    assert_eq!(outbox[0].recipient, "ursula_le_guin@gmail.com");
+    assert_eq!(outbox[0].subject, "Welcome!");
+}
+
+#[tokio::test]
+async fn failed_confirmation_emails_are_retried_by_the_worker() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+
+    // Act - Part 1 - The email provider is down
+    {
+        let _mock_guard = Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount_as_scoped(&app.email_server)
+            .await;
+        let outcome = try_send_outbox_emails(
+            &app.db_pool,
+            &app.email_client,
+            &app.base_url,
+            &app.hmac_secret,
+            &app.worker_settings,
+            &app.rate_limiter,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(outcome, ExecutionOutcome::TaskCompleted));
+    }
+
+    // Assert - Part 1
+    let saved = sqlx::query!("SELECT n_retries FROM email_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.n_retries, 1);
+
+    // Act - Part 2 - It is back up
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let n_pending = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM email_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_pending, 0);
+}
+
 #[tokio::test]
 async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Arrange
@@ -154,3 +237,234 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Assert
     assert_eq!(response.status().as_u16(), 500);
 }
+
+#[tokio::test]
+async fn subscribing_again_while_pending_resends_the_same_confirmation_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let first_response = app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+    let cooldown = app
+        .configuration
+        .subscriptions
+        .confirmation_resend_cooldown();
+    sqlx::query!(
+        "UPDATE subscriptions SET confirmation_requested_at = $1",
+        chrono::Utc::now() - cooldown
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let second_response = app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(first_response.status().as_u16(), 200);
+    assert_eq!(second_response.status().as_u16(), 200);
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let first_links = app.get_confirmation_links(&email_requests[0]);
+    let second_links = app.get_confirmation_links(&email_requests[1]);
+    assert_eq!(first_links.html, second_links.html);
+    let n_subscribers = sqlx::query!("SELECT count(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_subscribers, Some(1));
+}
+
+#[tokio::test]
+async fn subscribing_again_within_the_cooldown_does_not_send_another_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    for _ in 0..5 {
+        let response = app.post_subscriptions(body.into()).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    // Mock verifies that a single confirmation email was sent
+}
+
+#[tokio::test]
+async fn subscribing_again_once_confirmed_succeeds_without_sending_an_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+    // Mock verifies that only the first confirmation email was sent
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_come_back_through_a_fresh_opt_in() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let old_links = app.get_confirmation_links(email_request);
+    reqwest::get(old_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed', unsubscribed_at = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act - Part 1 - Subscribe again
+    let response = app
+        .post_subscriptions("name=ursula&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT name, status, unsubscribed_at FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "ursula");
+    assert_eq!(saved.status, "pending_confirmation");
+    assert!(saved.unsubscribed_at.is_none());
+    app.dispatch_all_pending_emails().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let new_links = app.get_confirmation_links(email_request);
+    assert_ne!(old_links.html, new_links.html);
+
+    // Act - Part 2 - The old link no longer works
+    let response = reqwest::get(old_links.html).await.unwrap();
+
+    // Assert - Part 2
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Act - Part 3 - The new one does
+    reqwest::get(new_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert - Part 3
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn confirmation_emails_exceeding_the_retry_budget_are_dead_lettered_and_can_be_requeued() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    app.login_with_test_user().await;
+    let max_attempts = u64::try_from(app.worker_settings.max_retries).unwrap() + 1;
+    let failing_mock = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(max_attempts)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Exhaust the retry budget
+    app.dispatch_all_pending_emails().await;
+    drop(failing_mock);
+
+    // Assert - Part 1
+    let n_pending = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM email_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_pending, 0);
+    let dead_letter =
+        sqlx::query!("SELECT id, recipient, n_retries FROM email_outbox_dead_letters")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(dead_letter.recipient, "ursula_le_guin@gmail.com");
+    assert_eq!(dead_letter.n_retries, app.worker_settings.max_retries);
+    let html_page = app.get_dead_letters_html().await;
+    assert!(html_page.contains("<td>ursula_le_guin@gmail.com</td>"));
+
+    // Act - Part 2 - Re-enqueue the email
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_requeue_email_dead_letter(&serde_json::json!({ "id": dead_letter.id }))
+        .await;
+    assert_is_redirected_to(&response, "/admin/dead_letters");
+    let html_page = app.get_dead_letters_html().await;
+    assert!(html_page.contains("<p><i>The email has been re-enqueued.</i></p>"));
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let n_dead_letters = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM email_outbox_dead_letters")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_dead_letters, 0);
+    // Mock verifies the re-enqueued email was sent
+}
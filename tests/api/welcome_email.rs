@@ -0,0 +1,300 @@
+use uuid::Uuid;
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{
+    assert_is_redirected_to, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+    TestApp,
+};
+
+async fn enable_welcome_email(app: &TestApp, include_latest_issue: bool) {
+    let mut body = serde_json::json!({
+        "enabled": "on",
+        "subject": "Welcome aboard",
+        "html_content": "<p>Welcome {{ subscriber.name }}!</p>",
+        "text_content": "Welcome {{ subscriber.name }}!",
+    });
+    if include_latest_issue {
+        body["include_latest_issue"] = "on".into();
+    }
+    let response = app.post_welcome_email(&body).await;
+    assert_is_redirected_to(&response, "/admin/welcome_email");
+}
+
+fn last_email_body(requests: &[wiremock::Request]) -> serde_json::Value {
+    serde_json::from_slice(&requests.last().unwrap().body).unwrap()
+}
+
+#[tokio::test]
+async fn no_welcome_email_is_sent_until_it_is_enabled() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    // Mock verifies on Drop that no welcome email was sent
+}
+
+#[tokio::test]
+async fn confirming_a_subscription_enqueues_a_welcome_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    enable_welcome_email(&app, false).await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    let name = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .name;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Confirm
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert - Part 1 - Nothing is sent inline
+    let n_queued = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM email_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_queued, 1);
+    assert_eq!(app.email_server.received_requests().await.unwrap().len(), 1);
+
+    // Act - Part 2 - Let the worker run
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body = last_email_body(&requests);
+    assert_eq!(body["Subject"], "Welcome aboard");
+    assert_eq!(
+        body["HtmlBody"],
+        format!("<p>Welcome {}!</p>", htmlescape::encode_attribute(&name))
+    );
+    assert_eq!(body["TextBody"], format!("Welcome {name}!"));
+    let headers = body["Headers"].as_array().unwrap();
+    assert!(headers.iter().any(|h| h["Name"] == "List-Unsubscribe"));
+}
+
+#[tokio::test]
+async fn welcome_emails_can_include_the_latest_public_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    enable_welcome_email(&app, true).await;
+    for (title, hide_from_archive) in [
+        ("Older issue", false),
+        ("Latest issue", false),
+        ("Private issue", true),
+    ] {
+        let mut body = serde_json::json!({
+            "title": title,
+            "html_content": format!("<p>{title} as HTML</p>"),
+            "text_content": format!("{title} as plain text"),
+            "idempotency_key": Uuid::new_v4().to_string()
+        });
+        if hide_from_archive {
+            body["hide_from_archive"] = "on".into();
+        }
+        app.post_publish_newsletter(body).await;
+    }
+    create_unconfirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriber_action(subscriber_id, "confirm").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body = last_email_body(&requests);
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    assert!(html_body.contains("<h2>Latest issue</h2>\n<p>Latest issue as HTML</p>"));
+    assert!(!html_body.contains("Older issue"));
+    assert!(!html_body.contains("Private issue"));
+    let text_body = body["TextBody"].as_str().unwrap();
+    assert!(text_body.contains("Latest issue\n\nLatest issue as plain text"));
+}
+
+#[tokio::test]
+async fn no_welcome_email_is_queued_for_subscribers_who_left_before_confirming() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    enable_welcome_email(&app, false).await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    app.post_subscriber_action(subscriber_id, "unsubscribe")
+        .await;
+
+    // Act
+    reqwest::get(confirmation_links.html).await.unwrap();
+
+    // Assert
+    let n_queued = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM email_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_queued, 0);
+}
+
+#[tokio::test]
+async fn no_welcome_email_is_sent_to_subscribers_who_leave_before_it_goes_out() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+    enable_welcome_email(&app, false).await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    app.post_subscriber_action(subscriber_id, "unsubscribe")
+        .await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let n_queued = sqlx::query!("SELECT COUNT(*) AS \"n!\" FROM email_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_queued, 0);
+}
+
+#[tokio::test]
+async fn welcome_emails_carried_over_from_the_old_queue_are_rendered_before_sending() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login_with_test_user().await;
+    enable_welcome_email(&app, false).await;
+    let subscriber = sqlx::query!("SELECT id, email, name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    // What the migration away from `welcome_email_queue` leaves in the outbox
+    sqlx::query!(
+        "INSERT INTO email_outbox \
+        (id, recipient, subject, html_content, text_content, header_names, header_values, \
+        welcome_subscriber_id) \
+        VALUES ($1, $2, '', '', '', '{}', '{}', $3)",
+        Uuid::new_v4(),
+        subscriber.email,
+        subscriber.id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body = last_email_body(&requests);
+    assert_eq!(body["To"], subscriber.email);
+    assert_eq!(body["Subject"], "Welcome aboard");
+    assert_eq!(body["TextBody"], format!("Welcome {}!", subscriber.name));
+    assert!(body["Headers"][0]["Value"]
+        .as_str()
+        .unwrap()
+        .contains("/subscriptions/unsubscribe?unsubscribe_token="));
+}
+
+#[tokio::test]
+async fn admins_can_edit_the_welcome_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_with_test_user().await;
+
+    // Act - Part 1 - Save
+    enable_welcome_email(&app, true).await;
+
+    // Assert - Part 1
+    let html_page = app.get_welcome_email_html().await;
+    assert!(html_page.contains("<p><i>The welcome email has been saved.</i></p>"));
+    assert!(html_page.contains(r#"<input type="checkbox" name="enabled" checked>"#));
+    assert!(html_page.contains("&lt;p&gt;Welcome {{ subscriber.name }}!&lt;/p&gt;"));
+
+    // Act - Part 2 - Invalid templates are rejected
+    let response = app
+        .post_welcome_email(&serde_json::json!({
+            "subject": "Welcome aboard",
+            "html_content": "<p>Welcome {{ subscriber.nickname }}!</p>",
+            "text_content": "Welcome!",
+        }))
+        .await;
+
+    // Assert - Part 2
+    assert_is_redirected_to(&response, "/admin/welcome_email");
+    let html_page = app.get_welcome_email_html().await;
+    assert!(html_page.contains("The HTML content is not a valid template."));
+    assert!(html_page.contains(r#"<input type="checkbox" name="enabled" checked>"#));
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_edit_the_welcome_email() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_welcome_email().await;
+
+    // Assert
+    assert_is_redirected_to(&response, "/login");
+}